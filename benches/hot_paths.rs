@@ -0,0 +1,100 @@
+/*
+ * Benchmarks for the hot paths of the tracer, to catch slowdowns before
+ * they get merged:
+ *
+ *     cargo bench --bench hot_paths
+ *     cargo bench --bench hot_paths -- --save-baseline main   # on main
+ *     cargo bench --bench hot_paths -- --baseline main        # on the branch
+ *
+ * The scene is `random_world` at a fixed seed, so runs are comparable.
+ */
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use tinywasm_playground::aov::Aov;
+use tinywasm_playground::primitives::{Float, Ray, Rect, Vec2i, Vec3};
+use tinywasm_playground::random;
+use tinywasm_playground::renderer::{RenderProperties, Tile};
+use tinywasm_playground::scene::{HitRecord, Material, Scene};
+use tinywasm_playground::spheres::{Sphere, Spheres};
+
+const SEED: u64 = 42;
+
+fn scene() -> Scene {
+    random::seed(SEED);
+    return Scene::random_scene(1.5);
+}
+
+// Straight through the middle of the image, where the big spheres are
+fn center_ray(scene: &Scene) -> Ray {
+    scene.camera.get_pinhole_ray(0.5, 0.5)
+}
+
+// A store with just the one sphere in front of the origin
+fn one_sphere() -> Spheres {
+    let mut spheres = Spheres::default();
+    spheres.push(Sphere { center: Vec3::new(0.0, 0.0, -1.0), radius: 0.5, material: 0 });
+    return spheres;
+}
+
+fn bench_hit(c: &mut Criterion) {
+    let sphere = one_sphere();
+    let ray = Ray { orig: Vec3::zero(), dir: Vec3::new(0.0, 0.0, -1.0) };
+    let miss = Ray { orig: Vec3::zero(), dir: Vec3::new(0.0, 1.0, 0.0) };
+    c.bench_function("hit/sphere", |b| b.iter(|| black_box(&sphere).hit(black_box(ray), 0.0, Float::INFINITY)));
+    c.bench_function("hit/sphere_miss", |b| b.iter(|| black_box(&sphere).hit(black_box(miss), 0.0, Float::INFINITY)));
+
+    let scene = scene();
+    let ray = center_ray(&scene);
+    c.bench_function("hit/random_world", |b| {
+        b.iter(|| black_box(&scene.world).hit(black_box(ray), 0.0, Float::INFINITY))
+    });
+}
+
+fn bench_scatter(c: &mut Criterion) {
+    let ray = Ray { orig: Vec3::zero(), dir: Vec3::new(0.0, 0.0, -1.0) };
+    let materials = [
+        ("lambertian", Material::Lambertian { albedo: Vec3::new(0.7, 0.6, 0.5) }),
+        ("metal", Material::Metal { albedo: Vec3::new(0.7, 0.6, 0.5), fuzz: 0.1 }),
+        ("dielectric", Material::Dielectric { index_refraction: 1.5, dispersion: 0.0 }),
+    ];
+    for (name, material) in materials {
+        let record: HitRecord = one_sphere().hit(ray, 0.0, Float::INFINITY).expect("The ray should hit the sphere");
+        c.bench_function(&format!("scatter/{}", name), |b| {
+            b.iter(|| {
+                let mut attenuation = Vec3::zero();
+                let mut scattered = Ray { orig: Vec3::zero(), dir: Vec3::zero() };
+                let bounced = material.scatter(black_box(ray), black_box(&record), &mut attenuation, &mut scattered);
+                black_box((bounced, attenuation, scattered))
+            })
+        });
+    }
+}
+
+fn bench_camera(c: &mut Criterion) {
+    let scene = scene();
+    c.bench_function("camera/get_ray", |b| {
+        b.iter(|| scene.camera.get_ray(black_box(0.25), black_box(0.75)))
+    });
+}
+
+fn bench_tile(c: &mut Criterion) {
+    let scene = scene();
+    let properties = RenderProperties {
+        samples: 4,
+        aovs: vec![Aov::Depth, Aov::Normal, Aov::Albedo],
+        ..Default::default()
+    };
+    let img_size = Vec2i::new(240, 160);
+    // the middle of the default canvas, the busiest part of the scene
+    let bounds = Rect { x: 108, y: 72, w: 24, h: 16 };
+    let mut group = c.benchmark_group("tile");
+    group.sample_size(20);
+    group.bench_function("render_tile", |b| {
+        b.iter(|| Tile::render_tile(bounds, img_size, &scene, &properties, 0))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_hit, bench_scatter, bench_camera, bench_tile);
+criterion_main!(benches);
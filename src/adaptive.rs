@@ -0,0 +1,335 @@
+/*
+ * Adaptive sampling.
+ *
+ * Every pixel keeps a running mean and variance of its samples. After a first
+ * pass with a few samples everywhere, the rest of the sample budget goes to
+ * the pixels whose confidence interval is still too wide, proportionally to
+ * how wide it is. Flat regions like the sky stop early and noisy ones
+ * (defocus, glossy reflections) get more samples.
+ */
+
+use crate::aov::Aov;
+use crate::film::Film;
+use crate::primitives::{Float, Rect, Vec2i, Vec3};
+use crate::renderer::{self, AovBuffers, RenderProperties, Tile};
+use crate::scene::Scene;
+use crate::stats::RenderStats;
+
+// z-score for a 95% confidence interval
+const CONFIDENCE_Z: f64 = 1.96;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveSampling {
+    pub min_samples: u32, // taken everywhere before looking at the variance
+    pub max_samples: u32, // per pixel cap
+    pub threshold: Float, // relative error at which a pixel counts as converged
+}
+
+/*
+ * Running statistics of a pixel's samples (Welford's algorithm).
+ *
+ * The variance is tracked on the luminance, which is what the eye (and the
+ * convergence test) cares about.
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PixelStats {
+    pub count: u32,
+    pub mean: Vec3,
+    lum_mean: f64,
+    lum_m2: f64, // sum of squared differences from the mean
+}
+
+impl Default for PixelStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            mean: Vec3::zero(),
+            lum_mean: 0.0,
+            lum_m2: 0.0,
+        }
+    }
+}
+
+fn luminance(c: Vec3) -> f64 {
+    (0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z) as f64
+}
+
+impl PixelStats {
+    pub fn add(&mut self, color: Vec3) {
+        self.count += 1;
+        self.mean += (color - self.mean) / self.count as Float;
+        let lum = luminance(color);
+        let delta = lum - self.lum_mean;
+        self.lum_mean += delta / self.count as f64;
+        self.lum_m2 += delta * (lum - self.lum_mean);
+    }
+
+    // Combines two independent sets of samples (Chan et al.)
+    pub fn merge(&mut self, other: &PixelStats) {
+        if other.count == 0 {
+            return;
+        }
+        let count = self.count + other.count;
+        let weight = other.count as f64 / count as f64;
+        let delta = other.lum_mean - self.lum_mean;
+        self.mean += (other.mean - self.mean) * weight as Float;
+        self.lum_mean += delta * weight;
+        self.lum_m2 += other.lum_m2 + delta * delta * self.count as f64 * weight;
+        self.count = count;
+    }
+
+    // Sample variance of the luminance
+    pub fn variance(&self) -> f64 {
+        if self.count < 2 {
+            return 0.0;
+        }
+        self.lum_m2 / (self.count - 1) as f64
+    }
+
+    /*
+     * Half width of the 95% confidence interval of the mean, relative to the
+     * mean. Dark pixels are measured against a floor of 0.1 instead, otherwise
+     * they would never converge.
+     */
+    pub fn relative_error(&self) -> Float {
+        if self.count < 2 {
+            return Float::INFINITY;
+        }
+        let half_width = CONFIDENCE_Z * (self.variance() / self.count as f64).sqrt();
+        (half_width / self.lum_mean.max(0.1)) as Float
+    }
+}
+
+/*
+ * Renders the image spending `properties.samples` samples per pixel on
+ * average, but distributed according to the noise.
+ */
+pub fn render_adaptive(
+    img_size: Vec2i,
+    scene: &Scene,
+    properties: &RenderProperties,
+    adaptive: &AdaptiveSampling,
+) -> AovBuffers {
+    let mut render = AdaptiveRender::new(img_size, properties, adaptive);
+    while render.render_next(scene) {}
+    return render.finish().0;
+}
+
+/*
+ * `render_adaptive()` a step at a time, for callers that need to get a word
+ * in between (like the async renderer handing control back to the browser).
+ * A step is a row of the first pass, or a row's worth of pixels of one of
+ * the passes handing out the rest of the budget.
+ */
+pub struct AdaptiveRender {
+    img_size: Vec2i,
+    first_pass: RenderProperties,
+    extra_pass: RenderProperties,
+    min_samples: u32,
+    max_samples: u32,
+    threshold: Float,
+    rows_left: i32, // of the first pass, top to bottom like the buffers
+    film: Film,
+    buffers: AovBuffers,
+    pixels: Vec<PixelStats>,
+    remaining: i64, // samples not handed out yet
+    queue: Vec<(usize, u32)>, // pixels and sample counts left in the current pass, last one first
+    stats: RenderStats,
+}
+
+impl AdaptiveRender {
+    pub fn new(img_size: Vec2i, properties: &RenderProperties, adaptive: &AdaptiveSampling) -> AdaptiveRender {
+        let min_samples = adaptive.min_samples.clamp(2, properties.samples.max(2));
+        let pixel_count = (img_size.x * img_size.y) as i64;
+        AdaptiveRender {
+            img_size,
+            // a few samples everywhere, this is also where the AOVs come from
+            first_pass: RenderProperties {
+                samples: min_samples,
+                adaptive: None,
+                ..properties.clone()
+            },
+            extra_pass: RenderProperties {
+                aovs: Vec::new(),
+                adaptive: None,
+                ..properties.clone()
+            },
+            min_samples,
+            max_samples: adaptive.max_samples.max(min_samples),
+            threshold: adaptive.threshold,
+            rows_left: img_size.y,
+            film: Film::new(Rect { x: 0, y: 0, w: img_size.x, h: img_size.y }),
+            buffers: AovBuffers::new(),
+            pixels: Vec::with_capacity(pixel_count as usize),
+            remaining: (properties.samples as i64 - min_samples as i64) * pixel_count,
+            queue: Vec::new(),
+            stats: RenderStats::new(img_size, properties.samples),
+        }
+    }
+
+    // Renders the next step, false once the image is done
+    pub fn render_next(&mut self, scene: &Scene) -> bool {
+        if self.rows_left > 0 {
+            self.rows_left -= 1;
+            let (tile, _) = self.stats.measure(|| {
+                Tile::render_line(self.rows_left, self.img_size, scene, &self.first_pass, 0)
+            });
+            self.film.merge(&tile.film);
+            for (aov, pixels) in tile.buffers {
+                self.buffers.entry(aov).or_default().extend(pixels);
+            }
+            self.pixels.extend(tile.stats);
+            return true;
+        }
+        if self.queue.is_empty() {
+            self.plan_pass();
+            if self.queue.is_empty() {
+                return false;
+            }
+        }
+        self.stats.measure(|| {
+            for _ in 0..self.img_size.x {
+                let Some((idx, count)) = self.queue.pop() else {
+                    break;
+                };
+                let coord = Vec2i {
+                    x: (idx % self.img_size.x as usize) as i32,
+                    y: self.img_size.y - 1 - (idx / self.img_size.x as usize) as i32, // first row is the top
+                };
+                let samples = self.pixels[idx].count..self.pixels[idx].count + count;
+                let sample = renderer::sample_pixel(coord, scene, &self.extra_pass, self.img_size, samples, &mut self.film);
+                self.pixels[idx].merge(&sample.stats);
+            }
+        });
+        return true;
+    }
+
+    // Hands out the next share of the budget to whatever is still noisy
+    fn plan_pass(&mut self) {
+        if self.remaining <= 0 {
+            return;
+        }
+        let active: Vec<(usize, Float)> = self.pixels.iter()
+            .enumerate()
+            .map(|(idx, pixel)| (idx, pixel.relative_error()))
+            .filter(|(idx, error)| self.pixels[*idx].count < self.max_samples && *error > self.threshold)
+            .collect();
+        if active.is_empty() {
+            return;
+        }
+        // noisier than average gets more than `min_samples` this pass, cleaner gets less
+        let mean_error = active.iter().map(|(_, error)| error.min(1e3)).sum::<Float>() / active.len() as Float;
+        let allocations: Vec<u32> = active.iter()
+            .map(|(idx, error)| {
+                let share = (self.min_samples as Float * error.min(1e3) / mean_error).round() as u32;
+                share.clamp(1, self.max_samples - self.pixels[*idx].count)
+            })
+            .collect();
+        let total: i64 = allocations.iter().map(|count| *count as i64).sum();
+        let budget_scale = (self.remaining as f64 / total as f64).min(1.0);
+
+        for ((idx, _), allocation) in active.iter().zip(allocations) {
+            let count = ((allocation as f64 * budget_scale).ceil() as i64).min(self.remaining);
+            if count <= 0 {
+                break;
+            }
+            self.queue.push((*idx, count as u32));
+            self.remaining -= count;
+        }
+        self.queue.reverse();
+    }
+
+    // Samples per pixel taken so far, on average
+    pub fn samples_per_pixel(&self) -> u32 {
+        let taken: u64 = self.pixels.iter().map(|pixel| pixel.count as u64).sum();
+        (taken / (self.img_size.x * self.img_size.y) as u64) as u32
+    }
+
+    // The color so far, black where nothing has been rendered yet
+    pub fn color(&self) -> Vec<Vec3> {
+        self.film.resolve()
+    }
+
+    pub fn finish(mut self) -> (AovBuffers, RenderStats) {
+        self.buffers.insert(Aov::Color, self.film.resolve());
+        if self.buffers.contains_key(&Aov::SampleCount) {
+            let counts = self.pixels.iter().map(|pixel| Vec3::ones() * pixel.count as Float).collect();
+            self.buffers.insert(Aov::SampleCount, counts);
+        }
+        return (self.buffers, self.stats);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random;
+
+    #[test]
+    fn test_running_stats() {
+        let mut stats = PixelStats::default();
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            stats.add(Vec3::ones() * value);
+        }
+        assert_eq!(stats.count, 4);
+        assert!((stats.mean - Vec3::ones() * 2.5).near_zero());
+        // luminance weights add up to 1, so this is the plain sample variance
+        assert!((stats.variance() - 5.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_merge_matches_sequential() {
+        let values = [0.5, 1.5, 0.25, 3.0, 2.0, 0.75];
+        let mut all = PixelStats::default();
+        values.iter().for_each(|v| all.add(Vec3::new(*v, *v * 0.5, 1.0)));
+
+        let (mut lhs, mut rhs) = (PixelStats::default(), PixelStats::default());
+        values[..2].iter().for_each(|v| lhs.add(Vec3::new(*v, *v * 0.5, 1.0)));
+        values[2..].iter().for_each(|v| rhs.add(Vec3::new(*v, *v * 0.5, 1.0)));
+        lhs.merge(&rhs);
+
+        assert_eq!(lhs.count, all.count);
+        assert!((lhs.mean - all.mean).near_zero());
+        assert!((lhs.variance() - all.variance()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_stepped_render_spends_the_budget() {
+        let img_size = Vec2i::new(16, 12);
+        random::seed(3);
+        let scene = Scene::random_scene(1.5);
+        let properties = RenderProperties {
+            samples: 8,
+            bounces: 8,
+            aovs: vec![Aov::SampleCount],
+            roulette_depth: None,
+            ..Default::default()
+        };
+        let adaptive = AdaptiveSampling { min_samples: 4, max_samples: 64, threshold: 0.01 };
+        let mut render = AdaptiveRender::new(img_size, &properties, &adaptive);
+        let mut steps = 0;
+        while render.render_next(&scene) {
+            steps += 1;
+        }
+        // the first pass takes a step per row, the rest of the budget more
+        assert!(steps > img_size.y, "{} steps", steps);
+        assert_eq!(render.samples_per_pixel(), 8);
+        let (buffers, _) = render.finish();
+        let counts = &buffers[&Aov::SampleCount];
+        assert!(counts.iter().all(|count| count.x >= 4.0));
+        assert!(counts.iter().any(|count| count.x > 8.0));
+    }
+
+    #[test]
+    fn test_converged_pixel() {
+        let mut flat = PixelStats::default();
+        (0..4).for_each(|_| flat.add(Vec3::ones() * 0.7));
+        assert_eq!(flat.relative_error(), 0.0);
+
+        let mut noisy = PixelStats::default();
+        [0.0, 1.0, 0.0, 1.0].iter().for_each(|v| noisy.add(Vec3::ones() * *v));
+        assert!(noisy.relative_error() > 0.5);
+
+        assert_eq!(PixelStats::default().relative_error(), Float::INFINITY);
+    }
+}
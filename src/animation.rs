@@ -0,0 +1,362 @@
+/*
+ * Keyframe animation of the camera and the objects.
+ *
+ * Every track is a list of keys at (possibly fractional) frame numbers.
+ * Between two keys the value is interpolated the way the first of the two
+ * asks for: a straight line, or a cubic Bezier curve whose control points
+ * come from the neighboring keys (Catmull-Rom style), so the motion goes
+ * smoothly through the keys. Before the first key and after the last one the
+ * value holds still.
+ *
+ * Animations can be read from a text file, one key per line:
+ *
+ *   # camera <frame> <linear|bezier> <lookfrom xyz> <lookat xyz> <vfov> <aperture> <focus_dist>
+ *   camera 0  bezier 13 2 3   0 0 0  20 0.1 10
+ *   camera 48 bezier 3 2 13   0 0 0  20 0.1 10
+ *   # object <id> <frame> <linear|bezier> <translation xyz> <scale>
+ *   object 3 0  linear 0 0 0  1
+ *   object 3 24 linear 0 1 0  1.5
+ *
+ * Object transforms are relative to where the object is in the scene.
+ */
+
+use crate::primitives::{Float, Vec3};
+use crate::scene::{Camera, Scene};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Interpolation {
+    Linear,
+    Bezier,
+}
+
+impl Interpolation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::Linear => "linear",
+            Interpolation::Bezier => "bezier",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Interpolation> {
+        [Interpolation::Linear, Interpolation::Bezier].into_iter().find(|kind| kind.name() == name)
+    }
+}
+
+// Values that can be keyframed: the curves only need to add and scale them
+pub trait Keyable: Copy {
+    fn add(self, other: Self) -> Self;
+    fn scale(self, factor: Float) -> Self;
+
+    fn lerp(self, other: Self, t: Float) -> Self {
+        self.scale(1.0 - t).add(other.scale(t))
+    }
+}
+
+impl Keyable for Float {
+    fn add(self, other: Float) -> Float {
+        self + other
+    }
+
+    fn scale(self, factor: Float) -> Float {
+        self * factor
+    }
+}
+
+impl Keyable for Vec3 {
+    fn add(self, other: Vec3) -> Vec3 {
+        self + other
+    }
+
+    fn scale(self, factor: Float) -> Vec3 {
+        self * factor
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Key<T> {
+    pub frame: Float,
+    pub value: T,
+    pub interpolation: Interpolation, // toward the next key
+}
+
+#[derive(Clone, Debug)]
+pub struct Track<T> {
+    keys: Vec<Key<T>>, // sorted by frame
+}
+
+impl<T: Keyable> Default for Track<T> {
+    fn default() -> Self {
+        Track::new()
+    }
+}
+
+impl<T: Keyable> Track<T> {
+    pub fn new() -> Track<T> {
+        Track { keys: Vec::new() }
+    }
+
+    // Replaces the key at the same frame, if there is one
+    pub fn insert(&mut self, key: Key<T>) {
+        match self.keys.binary_search_by(|other| other.frame.total_cmp(&key.frame)) {
+            Ok(idx) => self.keys[idx] = key,
+            Err(idx) => self.keys.insert(idx, key),
+        }
+    }
+
+    pub fn keys(&self) -> &[Key<T>] {
+        &self.keys
+    }
+
+    // Nothing for an empty track
+    pub fn sample(&self, frame: Float) -> Option<T> {
+        let first = self.keys.first()?;
+        let last = self.keys.last()?;
+        if frame <= first.frame {
+            return Some(first.value);
+        }
+        if frame >= last.frame {
+            return Some(last.value);
+        }
+        // the segment `idx..idx + 1` contains the frame
+        let idx = self.keys.partition_point(|key| key.frame <= frame) - 1;
+        let (from, to) = (&self.keys[idx], &self.keys[idx + 1]);
+        let t = (frame - from.frame) / (to.frame - from.frame);
+        match from.interpolation {
+            Interpolation::Linear => return Some(from.value.lerp(to.value, t)),
+            Interpolation::Bezier => {
+                let before = if idx > 0 { self.keys[idx - 1].value } else { from.value };
+                let after = self.keys.get(idx + 2).map_or(to.value, |key| key.value);
+                // Catmull-Rom tangents, as Bezier control points
+                let control1 = from.value.add(to.value.add(before.scale(-1.0)).scale(1.0 / 6.0));
+                let control2 = to.value.add(after.add(from.value.scale(-1.0)).scale(-1.0 / 6.0));
+                return Some(bezier(from.value, control1, control2, to.value, t));
+            },
+        }
+    }
+}
+
+// de Casteljau
+fn bezier<T: Keyable>(p0: T, p1: T, p2: T, p3: T, t: Float) -> T {
+    let (a, b, c) = (p0.lerp(p1, t), p1.lerp(p2, t), p2.lerp(p3, t));
+    let (d, e) = (a.lerp(b, t), b.lerp(c, t));
+    return d.lerp(e, t);
+}
+
+// The parameters of `Camera::new()` that make sense to animate
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraKey {
+    pub lookfrom: Vec3,
+    pub lookat: Vec3,
+    pub vfov: Float, // degrees
+    pub aperture: Float,
+    pub focus_dist: Float,
+}
+
+impl CameraKey {
+    pub fn camera(&self, aspect_ratio: Float) -> Camera {
+        Camera::new(
+            self.lookfrom,
+            self.lookat,
+            Vec3::new(0.0, 1.0, 0.0),
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        )
+    }
+}
+
+impl Keyable for CameraKey {
+    fn add(self, other: CameraKey) -> CameraKey {
+        CameraKey {
+            lookfrom: self.lookfrom + other.lookfrom,
+            lookat: self.lookat + other.lookat,
+            vfov: self.vfov + other.vfov,
+            aperture: self.aperture + other.aperture,
+            focus_dist: self.focus_dist + other.focus_dist,
+        }
+    }
+
+    fn scale(self, factor: Float) -> CameraKey {
+        CameraKey {
+            lookfrom: self.lookfrom * factor,
+            lookat: self.lookat * factor,
+            vfov: self.vfov * factor,
+            aperture: self.aperture * factor,
+            focus_dist: self.focus_dist * factor,
+        }
+    }
+}
+
+// Moves and scales an object, relative to where it is in the scene
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub scale: Float,
+}
+
+impl Keyable for Transform {
+    fn add(self, other: Transform) -> Transform {
+        Transform {
+            translation: self.translation + other.translation,
+            scale: self.scale + other.scale,
+        }
+    }
+
+    fn scale(self, factor: Float) -> Transform {
+        Transform {
+            translation: self.translation * factor,
+            scale: self.scale * factor,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Animation {
+    pub camera: Track<CameraKey>,
+    pub objects: Vec<(u32, Track<Transform>)>, // by object ID
+}
+
+impl Animation {
+    pub fn object_track(&mut self, id: u32) -> &mut Track<Transform> {
+        let idx = match self.objects.iter().position(|(object_id, _)| *object_id == id) {
+            Some(idx) => idx,
+            None => {
+                self.objects.push((id, Track::new()));
+                self.objects.len() - 1
+            },
+        };
+        return &mut self.objects[idx].1;
+    }
+
+    /*
+     * Poses `scene` at `frame`. The transforms are relative, so `scene`
+     * should be the scene at rest (e.g. a fresh clone for every frame).
+     */
+    pub fn apply(&self, scene: &mut Scene, frame: Float, aspect_ratio: Float) {
+        if let Some(key) = self.camera.sample(frame) {
+            // the keys only move the camera around, the model stays
+            scene.camera = key.camera(aspect_ratio).with_model(scene.camera.model);
+        }
+        for (id, track) in &self.objects {
+            let (Some(transform), Some(sphere)) = (track.sample(frame), scene.object(*id)) else {
+                continue;
+            };
+            scene.set_sphere(*id, sphere.center + transform.translation, sphere.radius * transform.scale);
+        }
+    }
+
+    // Reads the text format described at the top of this file
+    pub fn parse(text: &str) -> Result<Animation, String> {
+        let mut animation = Animation::default();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |msg: &str| format!("Line {}: {}", line_idx + 1, msg);
+            let mut fields = line.split_whitespace();
+            let kind = fields.next().unwrap_or("");
+            let id = match kind {
+                "camera" => None,
+                "object" => Some(next_number(&mut fields).ok_or(error("missing object ID"))? as u32),
+                _ => return Err(error(&format!("unknown track: {}", kind))),
+            };
+            let frame = next_number(&mut fields).ok_or(error("missing frame"))?;
+            let name = fields.next().unwrap_or("");
+            let interpolation = Interpolation::from_name(name)
+                .ok_or(error(&format!("unknown interpolation: {}", name)))?;
+            let values: Vec<Float> = fields
+                .map(|field| field.parse().map_err(|_| error(&format!("invalid number: {}", field))))
+                .collect::<Result<_, _>>()?;
+            match (id, values.as_slice()) {
+                (None, [fx, fy, fz, ax, ay, az, vfov, aperture, focus_dist]) => {
+                    let value = CameraKey {
+                        lookfrom: Vec3::new(*fx, *fy, *fz),
+                        lookat: Vec3::new(*ax, *ay, *az),
+                        vfov: *vfov,
+                        aperture: *aperture,
+                        focus_dist: *focus_dist,
+                    };
+                    animation.camera.insert(Key { frame, value, interpolation });
+                },
+                (Some(id), [x, y, z, scale]) => {
+                    let value = Transform { translation: Vec3::new(*x, *y, *z), scale: *scale };
+                    animation.object_track(id).insert(Key { frame, value, interpolation });
+                },
+                (None, _) => return Err(error("camera keys need 9 values")),
+                (Some(_), _) => return Err(error("object keys need 4 values")),
+            }
+        }
+        return Ok(animation);
+    }
+}
+
+fn next_number<'a>(fields: &mut impl Iterator<Item = &'a str>) -> Option<Float> {
+    fields.next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scene::{Hittable, Material};
+
+    fn track(interpolation: Interpolation) -> Track<Float> {
+        let mut track = Track::new();
+        for (frame, value) in [(0.0, 0.0), (10.0, 1.0), (20.0, 3.0), (30.0, 3.0)] {
+            track.insert(Key { frame, value, interpolation });
+        }
+        track
+    }
+
+    #[test]
+    fn test_linear_track() {
+        let track = track(Interpolation::Linear);
+        assert_eq!(track.sample(-5.0), Some(0.0));
+        assert_eq!(track.sample(5.0), Some(0.5));
+        assert_eq!(track.sample(15.0), Some(2.0));
+        assert_eq!(track.sample(40.0), Some(3.0));
+        assert_eq!(Track::<Float>::new().sample(1.0), None);
+    }
+
+    #[test]
+    fn test_bezier_goes_through_the_keys_smoothly() {
+        let track = track(Interpolation::Bezier);
+        for (frame, value) in [(0.0, 0.0), (10.0, 1.0), (20.0, 3.0), (30.0, 3.0)] {
+            assert!((track.sample(frame).unwrap() - value).abs() < 1e-5);
+        }
+        // the slope is continuous across a key
+        let slope = |frame: Float| (track.sample(frame + 0.01).unwrap() - track.sample(frame - 0.01).unwrap()) / 0.02;
+        assert!((slope(9.9) - slope(10.1)).abs() < 0.01, "{} vs {}", slope(9.9), slope(10.1));
+        // and eases in, unlike the linear one
+        assert!(track.sample(1.0).unwrap() < 0.1);
+    }
+
+    #[test]
+    fn test_parse_and_apply() {
+        let animation = Animation::parse("
+            # the camera moves up, the sphere grows
+            camera 0  linear 0 0 10  0 0 0  40 0 10
+            camera 10 linear 0 10 10  0 0 0  40 0 10
+            object 0 0  bezier 0 0 0 1
+            object 0 10 bezier 1 0 0 3 # comments at the end too
+        ").unwrap();
+        let material = Material::Lambertian { albedo: Vec3::ones() };
+        let base = Scene::new(
+            CameraKey { lookfrom: Vec3::zero(), lookat: -Vec3::ones(), vfov: 40.0, aperture: 0.0, focus_dist: 1.0 }
+                .camera(1.0),
+            Hittable::Sphere { center: Vec3::new(0.0, 1.0, 0.0), radius: 0.5, material },
+        );
+        let mut scene = base.clone();
+        animation.apply(&mut scene, 5.0, 1.0);
+        let expected = CameraKey { lookfrom: Vec3::new(0.0, 5.0, 10.0), lookat: Vec3::zero(), vfov: 40.0, aperture: 0.0, focus_dist: 10.0 };
+        assert_eq!(animation.camera.sample(5.0), Some(expected));
+        let sphere = scene.object(0).unwrap();
+        assert!((sphere.center - Vec3::new(0.5, 1.0, 0.0)).near_zero());
+        assert!((sphere.radius - 1.0).abs() < 1e-5);
+
+        assert!(Animation::parse("camera 0 linear 1 2 3").is_err());
+        assert!(Animation::parse("object 1 0 cubic 0 0 0 1").is_err());
+        assert!(Animation::parse("light 0 linear").is_err());
+    }
+}
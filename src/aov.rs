@@ -0,0 +1,209 @@
+/*
+ * Arbitrary output variables: extra render passes besides the color.
+ *
+ * Everything but the color comes from the first hit of the camera ray, and is
+ * stored in a `Vec3` per pixel like the color is (scalars in every channel).
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::compare::heat_color;
+use crate::primitives::{Float, Vec3};
+use crate::scene::{HitRecord, Scene};
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Aov {
+    Color,
+    Depth,      // distance along the camera's view axis to the pixel's nearest hit, infinite for misses
+    Normal,     // world space normal, facing the ray
+    Albedo,
+    ObjectId,   // the object's ID, which survives removing others (see `Scene`), -1 for misses
+    MaterialId, // handle into the scene's material library, -1 for misses
+    SampleCount, // samples taken for the pixel, interesting with adaptive sampling
+}
+
+// value for camera rays that hit nothing
+const MISS_ID: Float = -1.0;
+
+impl Aov {
+    pub const ALL: [Aov; 7] = [
+        Aov::Color,
+        Aov::Depth,
+        Aov::Normal,
+        Aov::Albedo,
+        Aov::ObjectId,
+        Aov::MaterialId,
+        Aov::SampleCount,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aov::Color => "color",
+            Aov::Depth => "depth",
+            Aov::Normal => "normal",
+            Aov::Albedo => "albedo",
+            Aov::ObjectId => "object_id",
+            Aov::MaterialId => "material_id",
+            Aov::SampleCount => "sample_count",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Aov> {
+        Aov::ALL.iter().find(|aov| aov.name() == name).copied()
+    }
+
+    // IDs can't be blended between samples, so they only come from the first one
+    pub fn is_id(&self) -> bool {
+        matches!(self, Aov::ObjectId | Aov::MaterialId)
+    }
+
+    // Whether the pass comes from the camera ray's first hit (see `first_hit_value`)
+    pub fn is_first_hit(&self) -> bool {
+        !matches!(self, Aov::Color | Aov::SampleCount)
+    }
+
+    // The value of this pass for a camera ray's first hit (not meant for `Color`)
+    pub fn first_hit_value(&self, scene: &Scene, hit: Option<&HitRecord>) -> Vec3 {
+        let Some(hit) = hit else {
+            return match self {
+                Aov::Depth => Vec3::ones() * Float::INFINITY,
+                Aov::ObjectId | Aov::MaterialId => Vec3::ones() * MISS_ID,
+                _ => Vec3::zero(),
+            };
+        };
+        match self {
+            Aov::Color | Aov::SampleCount => Vec3::zero(),
+            Aov::Depth => Vec3::ones() * scene.camera.view_depth(hit.p),
+            Aov::Normal => hit.normal,
+            Aov::Albedo => scene.material(hit.material).albedo(),
+            Aov::ObjectId => Vec3::ones() * scene.object_id(hit.object_index as usize).map_or(MISS_ID, |id| id as Float),
+            Aov::MaterialId => Vec3::ones() * hit.material as Float,
+        }
+    }
+
+    /*
+     * Turns the raw pass into something viewable, in 0..1.
+     *
+     * Not meant for `Color`, that one goes through the output stage instead.
+     */
+    pub fn visualize(&self, pixels: &[Vec3]) -> Vec<Vec3> {
+        match self {
+            Aov::Color | Aov::Albedo => pixels.to_vec(),
+            Aov::Depth => {
+                // near is bright, fading to black at the farthest hit
+                let max_depth = pixels.iter()
+                    .map(|px| px.x)
+                    .filter(|depth| depth.is_finite())
+                    .fold(0.0, Float::max);
+                pixels.iter().map(|px| {
+                    if px.x.is_finite() && max_depth > 0.0 {
+                        Vec3::ones() * (1.0 - px.x / max_depth)
+                    } else {
+                        Vec3::zero()
+                    }
+                }).collect()
+            },
+            Aov::Normal => pixels.iter()
+                .map(|n| (*n + Vec3::ones()) * 0.5)
+                .collect(),
+            Aov::ObjectId | Aov::MaterialId => pixels.iter()
+                .map(|px| id_color(px.x))
+                .collect(),
+            Aov::SampleCount => {
+                let max_count = pixels.iter().map(|px| px.x).fold(1.0, Float::max);
+                pixels.iter().map(|px| heat_color(px.x / max_count)).collect()
+            },
+        }
+    }
+}
+
+// Random looking, but stable, color for an ID
+fn id_color(id: Float) -> Vec3 {
+    if id < 0.0 {
+        return Vec3::zero();
+    }
+    // integer hash (lowbias32) so neighboring IDs look different
+    let mut h = id as u32;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846ca68b);
+    h ^= h >> 16;
+    let channel = |shift: u32| 0.2 + 0.8 * ((h >> shift) & 0xFF) as Float / 255.0;
+    Vec3::new(channel(0), channel(8), channel(16))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scene::Material;
+
+    #[test]
+    fn test_names_round_trip() {
+        for aov in Aov::ALL {
+            assert_eq!(Aov::from_name(aov.name()), Some(aov));
+        }
+        assert_eq!(Aov::from_name("beauty"), None);
+    }
+
+    #[test]
+    fn test_depth_visualization() {
+        let pixels = [
+            Vec3::ones() * 2.0,
+            Vec3::ones() * 4.0,
+            Vec3::ones() * Float::INFINITY,
+        ];
+        let shown = Aov::Depth.visualize(&pixels);
+        assert_eq!(shown[0], Vec3::ones() * 0.5);
+        assert_eq!(shown[1], Vec3::zero());
+        assert_eq!(shown[2], Vec3::zero());
+    }
+
+    // What `aov` gets for a hit on object `object_index` with `material`
+    fn hit_value(aov: Aov, scene: &Scene, object_index: u32, material: u32) -> Float {
+        let hit = HitRecord {
+            p: Vec3::new(0.0, 0.0, -1.0),
+            p_error: Vec3::zero(),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            material,
+            t: 1.0,
+            front_face: true,
+            object_index,
+        };
+        aov.first_hit_value(scene, Some(&hit)).x
+    }
+
+    #[test]
+    fn test_material_id_is_the_handle() {
+        let mut scene = Scene::random_scene(1.5);
+        let grey = Material::Lambertian { albedo: Vec3::ones() * 0.5 };
+        let first = scene.materials.add("first", grey).unwrap();
+        let second = scene.materials.add("second", grey).unwrap();
+        let id = |scene: &Scene, material| hit_value(Aov::MaterialId, scene, 0, material);
+        // the same parameters, but different entries
+        assert_ne!(id(&scene, first), id(&scene, second));
+        // and editing an entry doesn't change its ID
+        let before = id(&scene, first);
+        scene.materials.set(first, Material::Metal { albedo: Vec3::ones(), fuzz: 0.0 });
+        assert_eq!(id(&scene, first), before);
+    }
+
+    #[test]
+    fn test_object_id_survives_removal() {
+        let mut scene = Scene::random_scene(1.5);
+        let id = |scene: &Scene, index| hit_value(Aov::ObjectId, scene, index, 0);
+        let third = id(&scene, 2);
+        assert!(scene.remove_object(scene.object_id(0).unwrap()));
+        // the same object, one index down
+        assert_eq!(id(&scene, 1), third);
+        assert_eq!(third, scene.object_id(1).unwrap() as Float);
+    }
+
+    #[test]
+    fn test_id_colors() {
+        assert_eq!(id_color(MISS_ID), Vec3::zero());
+        assert_ne!(id_color(1.0), id_color(2.0));
+        assert_eq!(id_color(3.0), id_color(3.0));
+    }
+}
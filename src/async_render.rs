@@ -0,0 +1,126 @@
+/*
+ * Rendering without freezing the page.
+ *
+ * The render runs a tile at a time and gives control back to the browser
+ * (until the next animation frame) whenever it has used up a frame's worth
+ * of time, drawing what it has so far. It owns copies of everything it
+ * needs, since the `Renderer` can't be borrowed across the awaits; what it
+ * shares with it (the results, and whether it should stop) is behind `Rc`s.
+ */
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::adaptive::AdaptiveRender;
+use crate::aov::Aov;
+use crate::denoise::DenoiseSettings;
+use crate::output::OutputSettings;
+use crate::primitives::{Vec2i, Vec3};
+use crate::renderer::{AovBuffers, RenderProperties, TiledRender};
+use crate::scene::Scene;
+use crate::stats::RenderStats;
+
+// how long to render before letting the browser have a frame
+pub(crate) const FRAME_BUDGET_MS: f64 = 12.0;
+
+// Whichever way the samples get spread over the image
+enum Progress {
+    Uniform(TiledRender),
+    Adaptive(AdaptiveRender),
+}
+
+impl Progress {
+    fn render_next(&mut self, scene: &Scene) -> bool {
+        match self {
+            Progress::Uniform(render) => render.render_next(scene),
+            Progress::Adaptive(render) => render.render_next(scene),
+        }
+    }
+
+    fn color(&self) -> Vec<Vec3> {
+        match self {
+            Progress::Uniform(render) => render.color(),
+            Progress::Adaptive(render) => render.color(),
+        }
+    }
+
+    fn finish(self) -> (AovBuffers, RenderStats) {
+        match self {
+            Progress::Uniform(render) => render.finish(),
+            Progress::Adaptive(render) => render.finish(),
+        }
+    }
+}
+
+pub struct AsyncRender {
+    pub canvas_target: String,
+    pub size: Vec2i,
+    pub scene: Scene,
+    pub properties: RenderProperties,
+    pub output: OutputSettings,
+    pub display_pass: Aov,
+    pub denoise: Option<DenoiseSettings>,
+    pub buffers: Rc<RefCell<AovBuffers>>, // where the result goes
+    pub passes: Rc<Cell<u32>>,
+    pub stats: Rc<RefCell<RenderStats>>,
+    pub stats_overlay: bool,
+    pub running: Rc<Cell<bool>>,
+    pub cancelled: Rc<Cell<bool>>,
+}
+
+// Resolves on the next animation frame
+async fn next_frame() -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        let window = web_sys::window().expect("No window to render in");
+        if let Err(err) = window.request_animation_frame(&resolve) {
+            let _ = reject.call1(&JsValue::NULL, &err);
+        }
+    });
+    JsFuture::from(promise).await?;
+    Ok(())
+}
+
+impl AsyncRender {
+    pub async fn run(self) -> Result<JsValue, JsValue> {
+        let mut render = match &self.properties.adaptive {
+            Some(adaptive) => Progress::Adaptive(AdaptiveRender::new(self.size, &self.properties, adaptive)),
+            None => Progress::Uniform(TiledRender::new(self.size, &self.properties, 0)),
+        };
+        let mut slice_start = js_sys::Date::now();
+        loop {
+            let more = render.render_next(&self.scene);
+            if more && js_sys::Date::now() - slice_start < FRAME_BUDGET_MS {
+                continue;
+            }
+            if !more {
+                break;
+            }
+            let progress = self.output.to_rgba8(&render.color(), self.size.x);
+            crate::put_image(&self.canvas_target, self.size, &progress);
+            next_frame().await?;
+            if self.cancelled.get() {
+                return Err(js_sys::Error::new("Render stopped").into());
+            }
+            slice_start = js_sys::Date::now();
+        }
+
+        let (buffers, stats) = render.finish();
+        *self.buffers.borrow_mut() = buffers;
+        *self.stats.borrow_mut() = stats;
+        self.passes.set(self.properties.samples);
+        self.running.set(false);
+
+        let buffers = self.buffers.borrow();
+        if let Some(pixels) = crate::display_pixels(&buffers, self.display_pass, self.denoise.as_ref(), self.size) {
+            let pixel_bytes = crate::display_bytes(&pixels, self.display_pass, &self.output, self.size);
+            crate::put_image(&self.canvas_target, self.size, &pixel_bytes);
+            if self.stats_overlay {
+                crate::draw_stats(&self.canvas_target, &self.stats.borrow());
+            }
+        }
+        return Ok(self.stats.borrow().to_js());
+    }
+}
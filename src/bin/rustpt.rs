@@ -0,0 +1,444 @@
+/*
+ * Command line front end for the tracer.
+ *
+ * Renders the same scene as the web page and writes it to disk. The file
+ * format is picked from the output extension:
+ *  - .ppm, .png: 8-bit, through the same output stage as the canvas
+ *  - .hdr: Radiance RGBE, linear
+ *  - .exr: OpenEXR, linear
+ *
+ * With --frames it renders an animation instead, one numbered file per frame.
+ */
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use tinywasm_playground::adaptive::AdaptiveSampling;
+use tinywasm_playground::animation::Animation;
+use tinywasm_playground::aov::Aov;
+use tinywasm_playground::compare;
+use tinywasm_playground::denoise::{self, DenoiseSettings, Guides};
+use tinywasm_playground::film::{Filter, PixelFilter};
+use tinywasm_playground::hdr::{self, ExrCompression, ExrPixelType};
+use tinywasm_playground::image::Image;
+use tinywasm_playground::integrator::IntegratorKind;
+use tinywasm_playground::output::{OutputSettings, ToneMapper};
+use tinywasm_playground::primitives::{Float, Vec2i, Vec3};
+use tinywasm_playground::random;
+use tinywasm_playground::renderer::{self, AovBuffers, RenderProperties};
+use tinywasm_playground::sampler::Sampler;
+use tinywasm_playground::scene::{CameraModel, Scene};
+use tinywasm_playground::scene_file;
+use tinywasm_playground::spectrum::ColorMode;
+
+const USAGE: &str = "\
+Usage: rustpt [options] <output.{ppm,png,hdr,exr}>
+
+Options:
+    --width <px>           image width (default 240)
+    --height <px>          image height (default: 3:2 aspect ratio, 2:1 for equirectangular)
+    --camera <name>        perspective, orthographic, equirectangular or fisheye
+                           (default perspective)
+    --samples <n>          samples per pixel (default 10)
+    --bounces <n>          maximum bounces per path (default 50)
+    --integrator <name>    path, or a preview: ao, normals, distance (hit distance
+                           heatmap) or tests (intersection count heatmap)
+    --ao-radius <r>        occlusion distance for ao (default 1)
+    --max-distance <d>     hit distance shown white by distance (default 20)
+    --max-tests <n>        intersection count shown white by tests (default 1000)
+    --roulette <n>         bounces before Russian roulette may end a path (default 3)
+    --no-roulette          only end paths at the maximum bounce count
+    --seed <n>             seed for the random number generator
+    --pass <name>          which pass to write: color, depth, normal, albedo,
+                           object_id, material_id or sample_count (default color)
+    --adaptive <error>     spend the samples on noisy pixels until their relative
+                           error is below this, --samples becomes the average
+    --sampler <name>       random, stratified, halton, sobol or blue_noise (default sobol)
+    --filter <name>        pixel filter: box, tent, gaussian, mitchell or
+                           blackman_harris (default box)
+    --filter-radius <px>   filter radius, defaults to the filter's usual one
+    --spectral <n>         trace n wavelengths per sample instead of RGB
+    --denoise              denoise the color pass, guided by the albedo and normals
+    --exposure <stops>     exposure adjustment for .ppm/.png output (default 0)
+    --tone-mapper <name>   clamp, reinhard or aces, for .ppm/.png output (default clamp)
+    --no-dither            disable dithering for .ppm/.png output
+    --half                 write half floats to .exr (default float)
+    --zip                  ZIP compress .exr output
+    --compare <ppm>        print RMSE, PSNR and SSIM against a reference image
+    --heatmap <ppm>        with --compare, write the per-pixel error to this file
+    --keyframes <file>     camera and object keyframes to animate (see src/animation.rs)
+    --frames <n>..<m>      render frames n to m of the animation; a run of #s in
+                           the output name becomes the frame number (frame_###.png),
+                           otherwise it's added before the extension
+    --scene <file>         materials and spheres to render instead of the default
+                           scene, keeping its camera (see src/scene_file.rs)
+    --save-scene <file>    also write the scene's materials and spheres to a file
+    --stats                print rays, intersection tests and timings to stderr
+    --help                 show this message
+";
+
+struct Options {
+    width: i32,
+    height: i32, // the aspect ratio's for the camera if not given
+    camera: CameraModel,
+    render_config: RenderProperties,
+    seed: Option<u64>,
+    pass: Aov,
+    denoise: Option<DenoiseSettings>,
+    output: OutputSettings,
+    exr_pixel_type: ExrPixelType,
+    exr_compression: ExrCompression,
+    compare: Option<String>,
+    heatmap: Option<String>,
+    keyframes: Option<String>,
+    frames: Option<(u32, u32)>, // first and last, inclusive
+    scene: Option<String>,
+    save_scene: Option<String>,
+    stats: bool,
+    path: String,
+}
+
+// The extensions `write_image()` knows
+const OUTPUT_FORMATS: [&str; 4] = ["ppm", "png", "hdr", "exr"];
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or(format!("Missing value for {}", flag))?;
+    value.parse().map_err(|_| format!("Invalid value for {}: {}", flag, value))
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        width: 240,
+        height: 0,
+        camera: CameraModel::Perspective,
+        render_config: RenderProperties::default(),
+        seed: None,
+        pass: Aov::Color,
+        denoise: None,
+        output: OutputSettings::default(),
+        exr_pixel_type: ExrPixelType::Float,
+        exr_compression: ExrCompression::None,
+        compare: None,
+        heatmap: None,
+        keyframes: None,
+        frames: None,
+        scene: None,
+        save_scene: None,
+        stats: false,
+        path: String::new(),
+    };
+    let mut height = None;
+    let mut filter_radius = 0.0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => options.width = parse_value(&arg, args.next())?,
+            "--height" => height = Some(parse_value(&arg, args.next())?),
+            "--camera" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.camera = CameraModel::from_name(&name).ok_or(format!("Unknown camera: {}", name))?;
+            },
+            "--samples" => options.render_config.samples = parse_value(&arg, args.next())?,
+            "--bounces" => options.render_config.bounces = parse_value(&arg, args.next())?,
+            "--integrator" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.render_config.integrator.kind = IntegratorKind::from_name(&name).ok_or(format!("Unknown integrator: {}", name))?;
+            },
+            "--ao-radius" => options.render_config.integrator.ao_radius = parse_value(&arg, args.next())?,
+            "--max-distance" => options.render_config.integrator.max_distance = parse_value(&arg, args.next())?,
+            "--max-tests" => options.render_config.integrator.max_tests = parse_value(&arg, args.next())?,
+            "--roulette" => options.render_config.roulette_depth = Some(parse_value(&arg, args.next())?),
+            "--no-roulette" => options.render_config.roulette_depth = None,
+            "--seed" => options.seed = Some(parse_value(&arg, args.next())?),
+            "--pass" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.pass = Aov::from_name(&name).ok_or(format!("Unknown pass: {}", name))?;
+            },
+            "--adaptive" => {
+                let threshold = parse_value(&arg, args.next())?;
+                options.render_config.adaptive = Some(AdaptiveSampling {
+                    min_samples: 4,
+                    max_samples: 0, // filled in once --samples is known
+                    threshold,
+                });
+            },
+            "--sampler" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.render_config.sampler = Sampler::from_name(&name).ok_or(format!("Unknown sampler: {}", name))?;
+            },
+            "--filter" => {
+                let name: String = parse_value(&arg, args.next())?;
+                let filter = Filter::from_name(&name).ok_or(format!("Unknown filter: {}", name))?;
+                options.render_config.filter = PixelFilter::new(filter, filter_radius);
+            },
+            "--filter-radius" => {
+                filter_radius = parse_value(&arg, args.next())?;
+                options.render_config.filter = PixelFilter::new(options.render_config.filter.filter, filter_radius);
+            },
+            "--spectral" => {
+                let wavelengths = parse_value(&arg, args.next())?;
+                if wavelengths == 0 {
+                    return Err("--spectral needs at least 1 wavelength".to_string());
+                }
+                options.render_config.color_mode = ColorMode::Spectral { wavelengths };
+            },
+            "--denoise" => options.denoise = Some(DenoiseSettings::default()),
+            "--exposure" => options.output.exposure = parse_value(&arg, args.next())?,
+            "--tone-mapper" => {
+                let name: String = parse_value(&arg, args.next())?;
+                options.output.tone_mapper = match name.as_str() {
+                    "clamp" => ToneMapper::Clamp,
+                    "reinhard" => ToneMapper::Reinhard,
+                    "aces" => ToneMapper::AcesFilmic,
+                    _ => return Err(format!("Unknown tone mapper: {}", name)),
+                };
+            },
+            "--no-dither" => options.output.dither = false,
+            "--half" => options.exr_pixel_type = ExrPixelType::Half,
+            "--zip" => options.exr_compression = ExrCompression::Zip,
+            "--compare" => options.compare = Some(parse_value(&arg, args.next())?),
+            "--heatmap" => options.heatmap = Some(parse_value(&arg, args.next())?),
+            "--keyframes" => options.keyframes = Some(parse_value(&arg, args.next())?),
+            "--frames" => {
+                let range: String = parse_value(&arg, args.next())?;
+                let invalid = || format!("Invalid value for --frames: {}", range);
+                let (first, last) = range.split_once("..").ok_or_else(invalid)?;
+                let frames = (first.parse().map_err(|_| invalid())?, last.parse().map_err(|_| invalid())?);
+                if frames.0 > frames.1 {
+                    return Err(invalid());
+                }
+                options.frames = Some(frames);
+            },
+            "--scene" => options.scene = Some(parse_value(&arg, args.next())?),
+            "--save-scene" => options.save_scene = Some(parse_value(&arg, args.next())?),
+            "--stats" => options.stats = true,
+            "--help" => return Err(String::new()),
+            _ if arg.starts_with("--") => return Err(format!("Unknown option: {}", arg)),
+            _ => options.path = arg,
+        }
+    }
+    let samples = options.render_config.samples;
+    if let Some(adaptive) = options.render_config.adaptive.as_mut() {
+        adaptive.max_samples = samples * 8;
+    }
+    if options.pass != Aov::Color {
+        options.render_config.aovs.push(options.pass);
+    }
+    if options.denoise.is_some() {
+        // the denoiser's guides
+        options.render_config.aovs.extend([Aov::Albedo, Aov::Normal]);
+    }
+    if options.path.is_empty() {
+        return Err("Missing output file".to_string());
+    }
+    // checked now rather than after the render
+    if !OUTPUT_FORMATS.iter().any(|format| options.path.rsplit('.').next() == Some(format)) {
+        return Err(format!("Unsupported output format: {}", options.path));
+    }
+    if options.frames.is_some() && options.keyframes.is_none() {
+        return Err("--frames needs --keyframes".to_string());
+    }
+    if options.frames.is_some() && options.compare.is_some() {
+        return Err("--compare only works on single images".to_string());
+    }
+    let default_aspect_ratio = if options.camera == CameraModel::Equirectangular { 2.0 } else { 3.0 / 2.0 };
+    options.height = height.unwrap_or((options.width as Float / default_aspect_ratio) as i32);
+    if options.width < 2 || options.height < 2 || options.render_config.samples == 0 {
+        return Err("Width and height must be at least 2 and samples at least 1".to_string());
+    }
+    return Ok(options);
+}
+
+// The pass as it would show on the canvas, for the 8-bit formats
+fn display_image(pixels: &[Vec3], size: Vec2i, options: &Options) -> Image {
+    let pixels = if options.pass != Aov::Color {
+        options.pass.visualize(pixels)
+    } else {
+        // already quantized (and dithered), the writers only scale it back up
+        pixels.iter().enumerate()
+            .map(|(idx, pixel)| {
+                let coord = Vec2i::new(idx as i32 % size.x, idx as i32 / size.x);
+                let [r, g, b] = options.output.map_pixel(*pixel, coord);
+                Vec3::new(r as Float, g as Float, b as Float) / 255.0
+            })
+            .collect()
+    };
+    return Image {
+        width: size.x as usize,
+        height: size.y as usize,
+        pixels,
+    };
+}
+
+// The render and the passes the output needs, denoised if asked to
+fn render(scene: &Scene, size: Vec2i, options: &Options) -> AovBuffers {
+    let (mut buffers, stats) = renderer::render_image_with_stats(size, scene, &options.render_config);
+    if options.stats {
+        for line in stats.summary() {
+            eprintln!("{}", line);
+        }
+    }
+    if let Some(settings) = &options.denoise {
+        let guides = Guides {
+            albedo: &buffers[&Aov::Albedo],
+            normal: &buffers[&Aov::Normal],
+        };
+        let denoised = denoise::denoise(
+            &buffers[&Aov::Color],
+            &guides,
+            size.x as usize,
+            size.y as usize,
+            settings,
+        );
+        buffers.insert(Aov::Color, denoised);
+    }
+    return buffers;
+}
+
+fn write_image(path: &str, pixels: &[Vec3], size: Vec2i, options: &Options) -> Result<(), String> {
+    let (width, height) = (size.x as usize, size.y as usize);
+    let bytes = match path.rsplit('.').next() {
+        Some("ppm") => display_image(pixels, size, options).to_ppm(),
+        Some("png") => display_image(pixels, size, options).to_png(),
+        Some("hdr") => hdr::encode_radiance_hdr(pixels, width, height),
+        Some("exr") => hdr::encode_openexr(
+            pixels,
+            width,
+            height,
+            options.exr_pixel_type,
+            options.exr_compression,
+        ),
+        _ => return Err(format!("Unsupported output format: {}", path)),
+    };
+    fs::write(path, bytes).map_err(|err| format!("Could not write {}: {}", path, err))
+}
+
+// `frame_###.png` becomes `frame_007.png`, `out.png` becomes `out_0007.png`
+fn frame_path(path: &str, frame: u32) -> String {
+    if let Some(last) = path.rfind('#') {
+        let end = last + 1;
+        let start = path[..end].trim_end_matches('#').len();
+        return format!("{}{:0width$}{}", &path[..start], frame, &path[end..], width = end - start);
+    }
+    match path.rfind('.') {
+        Some(dot) => format!("{}_{:04}{}", &path[..dot], frame, &path[dot..]),
+        None => format!("{}_{:04}", path, frame),
+    }
+}
+
+fn render_frames(base: &Scene, size: Vec2i, options: &Options, (first, last): (u32, u32)) -> Result<(), String> {
+    let keyframes = options.keyframes.as_deref().unwrap_or_default();
+    let text = fs::read_to_string(keyframes)
+        .map_err(|err| format!("Could not read {}: {}", keyframes, err))?;
+    let animation = Animation::parse(&text).map_err(|msg| format!("{}: {}", keyframes, msg))?;
+    for frame in first..=last {
+        let mut scene = base.clone();
+        animation.apply(&mut scene, frame as Float, size.x as Float / size.y as Float);
+        let buffers = render(&scene, size, options);
+        let path = frame_path(&options.path, frame);
+        write_image(&path, &buffers[&options.pass], size, options)?;
+        println!("{}", path);
+    }
+    Ok(())
+}
+
+// The objects and materials from a scene file, seen through `camera_scene`'s camera
+fn load_scene(path: &str, camera_scene: &Scene) -> Result<Scene, String> {
+    let text = fs::read_to_string(path).map_err(|err| format!("Could not read {}: {}", path, err))?;
+    return scene_file::read(camera_scene.camera, &text).map_err(|msg| format!("{}: {}", path, msg));
+}
+
+// Compares the tone mapped render against a (display referred) reference PPM
+fn compare(pixels: &[Vec3], size: Vec2i, options: &Options, reference_path: &str) -> Result<(), String> {
+    let bytes = fs::read(reference_path)
+        .map_err(|err| format!("Could not read {}: {}", reference_path, err))?;
+    let reference = Image::from_ppm(&bytes)?;
+    if reference.width != size.x as usize || reference.height != size.y as usize {
+        return Err(format!(
+            "Reference is {}x{} but the render is {}x{}",
+            reference.width, reference.height, size.x, size.y,
+        ));
+    }
+    let display = if options.pass == Aov::Color {
+        pixels.iter().map(|px| options.output.display_color(*px)).collect()
+    } else {
+        options.pass.visualize(pixels)
+    };
+    let render = Image {
+        width: size.x as usize,
+        height: size.y as usize,
+        pixels: display,
+    };
+    println!("RMSE: {:.5}", compare::rmse(&render, &reference));
+    println!("PSNR: {:.2} dB", compare::psnr(&render, &reference));
+    println!("SSIM: {:.4}", compare::ssim(&render, &reference));
+
+    if let Some(heatmap_path) = &options.heatmap {
+        let heatmap = compare::error_heatmap(&render, &reference, 0.1);
+        fs::write(heatmap_path, heatmap.to_ppm())
+            .map_err(|err| format!("Could not write {}: {}", heatmap_path, err))?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(msg) => {
+            // an empty message is --help, which isn't a mistake
+            if msg.is_empty() {
+                print!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            eprintln!("{}\n", msg);
+            eprint!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(seed) = options.seed {
+        random::seed(seed);
+    }
+
+    let size = Vec2i::new(options.width, options.height);
+    let mut scene = Scene::random_scene(size.x as Float / size.y as Float);
+    scene.camera.model = options.camera;
+    if let Some(path) = &options.scene {
+        match load_scene(path, &scene) {
+            Ok(loaded) => scene = loaded,
+            Err(msg) => {
+                eprintln!("{}", msg);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if let Some(path) = &options.save_scene {
+        if let Err(err) = fs::write(path, scene_file::write(&scene)) {
+            eprintln!("Could not write {}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(frames) = options.frames {
+        if let Err(msg) = render_frames(&scene, size, &options, frames) {
+            eprintln!("{}", msg);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let buffers = render(&scene, size, &options);
+    let pixels = &buffers[&options.pass];
+    if let Err(msg) = write_image(&options.path, pixels, size, &options) {
+        eprintln!("{}", msg);
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(reference_path) = &options.compare {
+        if let Err(msg) = compare(pixels, size, &options, reference_path) {
+            eprintln!("{}", msg);
+            return ExitCode::FAILURE;
+        }
+    }
+    return ExitCode::SUCCESS;
+}
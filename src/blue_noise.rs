@@ -0,0 +1,270 @@
+/*
+ * A 64x64 blue noise mask, each pixel's rank from 0 to 4095 (every rank
+ * shows up once, and similar ranks are spread far apart).
+ *
+ * Made once with void-and-cluster ("The void-and-cluster method for dither
+ * array generation", Ulichney 1993): a toroidal gaussian energy with a sigma
+ * of 1.5 pixels and an initial pattern of a tenth of the pixels. Searching
+ * the whole mask for every rank is far too slow to redo at startup.
+ */
+
+pub const SIZE: usize = 64;
+
+pub const MASK: [u16; SIZE * SIZE] = [
+     163, 1395, 4006,  635, 1583, 1005,  158, 4078, 2838, 1831, 2219,  906, 3474, 2782, 3697,  990,
+    1509, 3849, 2984,  291, 3282, 3819, 3062, 1135, 2657, 3139, 2346, 1307, 2796, 3157, 1567, 2683,
+      21, 2167,  832, 3770, 1706, 2455,  378, 3853, 1167, 3276, 2032,  455, 3879, 3392,  816, 1670,
+    2556,  948, 1843, 3991, 2478, 3196,  168, 1037,  743, 3132, 1650,  678, 1163,  289, 3895, 1729,
+    2223, 2813, 1896,  218, 2911, 3305, 2655, 2083,  623, 1098, 3824, 1596,  456, 1240, 1805, 3170,
+    2431,  756, 1253, 2574, 1042, 1763,  117, 1470, 3460,  898,  440, 3894,  121, 2109, 3713, 1138,
+    3029, 3947, 1376, 2753, 1058, 3562, 1490, 2602,  794, 2967, 1536, 2709, 1797,  185, 2407, 2047,
+    3229, 3543, 2778,  252, 1491, 2006, 3426, 2331, 4051, 2621,  266, 2268, 3787, 2561,  910, 3526,
+     681, 3769, 1113, 3437, 2050,  742, 1686, 1299, 3445,   27, 2618, 3224, 2329, 3955,  113, 2679,
+     472, 3433, 1859, 3780,  551, 2342, 2885, 4086,  347, 2217, 1710, 3364, 2511,  849, 1854,  443,
+    2352,  622, 1973,  151, 3006,  564, 2163, 3342, 1853,  301, 4066,  682, 1127, 2843, 3752,  342,
+    1286,  520, 2207, 1106, 3716,  569, 2833, 1335,  392, 1923, 3589, 1449, 2924, 1958, 1544, 3090,
+    2445,  355, 2702, 1425, 2403, 3880,  299, 3715, 2262, 3002, 1360,  733, 1921, 2974,  953, 3592,
+    1600, 2213,   10, 2764, 1516, 3331,  861, 1865, 2571, 3678, 1061, 2945,  527, 1391, 3507, 3231,
+    1521, 3646, 2591, 3471, 1758, 3929, 1260,   57, 3732, 2449, 1303, 3470, 2266, 3098, 1476,  897,
+    4040, 2990, 1621, 3272, 2626,  907, 1774, 3541, 3011, 1085, 3237,  818,   87, 3351,  422, 1214,
+    3386, 1703,  800, 3601,  434, 1044, 3133, 2550,  797, 1750, 4013,  386, 3435, 1492,  547, 2073,
+    1090, 3902, 3019,  908, 3657, 2059,  236, 1211, 3053,  673, 2045, 1518, 4010, 2763, 2198,  257,
+    2852,  804, 1213,  389, 2335,  831, 3143, 2798, 1003, 1656, 3153,  438, 1922,    6, 3604, 2589,
+    1887, 2345,  812,  371, 2051, 3914,   12, 2224,  697, 1687, 2406, 3862, 1337, 2704, 4081, 2039,
+       0, 3923, 2130, 3173, 1787, 2793, 1992, 1235,  234, 3369, 2044, 2828, 1165, 2522, 3823, 2814,
+    3258,  335, 2386, 1287,  405, 2607, 3453, 3884, 1441,   35, 3329, 2351,  192, 1177, 3765,  993,
+    1740, 3978, 2099, 3268, 1398, 2642, 2000,  523, 3574, 2102,  763, 2771, 3953, 1014, 1718,  587,
+    3358,  105, 3839, 3446, 1226, 3071, 1515, 2590, 3981,  242, 2826,  489, 2210, 1766,  885, 2993,
+    1454, 1158, 2883,  197, 1326, 4043,  514, 3514, 1551, 2714, 1034,  101, 3682, 2231,  216, 1786,
+     741, 1452, 1895, 4028, 3138, 1700,  757, 2310, 1901, 2728, 3747,  783, 3100, 1951,  552, 2531,
+    3116,   79, 2730,  676, 3749,  225, 4034, 1546, 2412,  141, 3800, 2512, 1383, 3220, 2200, 2818,
+    1123, 1421, 2720, 1749, 2421,  619, 3636,  971, 3184, 2074, 1472, 3420, 1107, 3645,  582, 2559,
+    3684, 2388,  610, 3788, 2564,  870, 2316, 2918, 3764,  597, 2168, 3128, 1685,  639, 3047, 1209,
+    3710, 2735, 3500,  641, 2178, 1137, 2923,  293, 3174,  560, 1233, 1734, 3596, 2691, 1564, 3457,
+    2029, 1378, 3578, 1661, 2959, 1871,  869, 3256, 1317, 3033, 1065, 1694,  296,  699, 3548,  226,
+    3746, 3065, 2082,  985,  159, 2873, 1966,  340, 1257, 3737,  780, 3016, 2438,  199, 3252, 1904,
+     295, 3324, 1016, 1959, 1528, 3238,   45, 1762,  965, 2456, 3882, 1323, 3472,  940, 4082, 2464,
+    2095,  186, 1021, 2475,   72, 3820, 1547, 3605, 1007, 4038, 2583, 2244,  363,  923, 3851,  300,
+     747, 2317, 1025,  418, 2365, 1171, 3509, 2567,  356, 1892, 3427, 2273, 3705, 2977, 1956, 2490,
+    1634,  407,  701, 3539, 4089, 1431, 3361, 2460, 1732, 2638,   67, 1870, 3931, 1563, 2795,  924,
+    2171, 1618, 2756, 3519,  424, 3694, 2087, 1358, 3186,  264, 1867,  449, 2699, 1967, 1498,  420,
+    3429, 1669, 2952, 3292, 1794, 2719,  481, 2423, 2027, 1646,  116, 3410, 1464, 2988, 2364, 1255,
+    3309, 4058, 2627, 3204, 3687,   28, 2098,  679, 2825, 3985,  519,  875, 2693, 1522, 1202,  883,
+    3993, 2284, 3261, 2593, 1116, 2251,  790, 3903,  566, 2937, 3515, 1018,  602, 2294, 1318, 4015,
+     696, 3084,  110, 2247, 1194, 2978,  738, 4000, 2664, 3528,  862, 3020, 2281,   30, 3286, 2877,
+    1129,  596, 3744, 1386,  786, 3415, 1201, 3003,  769, 3304, 2810, 1062, 3937,  637, 2005, 2794,
+    1725,  177, 1888,  654, 1435, 2856, 1653, 3766, 1207, 1577, 2114, 3321,   64, 3850,  461, 3397,
+    2747, 1295, 1816,  292, 3042, 1868,  190, 3122, 1140, 2230, 1453, 2080, 3616, 3049,  417, 3404,
+    1940, 1340, 3799,  860, 2648, 1623, 2363,  487, 1101, 2220, 1523, 3968, 1161, 3624,  729, 2174,
+    3948, 1905, 2377,  280, 2017, 4005, 2255,  152, 3808, 1381,  590, 2190, 1796, 3187,   38, 3643,
+     956, 3080, 1196, 2177, 3938,  941, 3336, 2384,  157, 3113, 2629, 1050, 1821, 2332, 3159, 2041,
+     122,  788, 3702, 1542,  614, 3741, 2773, 1586, 3659,  411, 3195,  274, 2708, 1781, 1132, 2547,
+    2914,  357, 3362, 1837, 3911,  164, 3623, 3107, 1795,   92, 3251,  609, 2770, 1733, 2528, 1380,
+     153, 2788,  891, 3165, 2608,  556, 1482, 3212, 1841, 2473, 3591,  271, 3447, 1216, 2563, 1550,
+     567, 3815, 2692, 3443,  466, 2541,  328, 1949,  851, 3520,  441, 3940, 1338, 2912,  617, 1615,
+    3564, 2938, 2129, 2503, 3380, 1268, 2392,  899, 1933, 2604, 4060, 1305,  820, 3873,   80, 3698,
+     752, 1657, 2434,  528, 3155, 1045, 2038, 1373, 2842, 3844, 2454, 1957,  217, 3789,  984, 3035,
+    3419, 1585, 3865, 1173, 3561, 1731,  905, 2669,  323, 1094, 2962, 1534, 2391,  857, 4004, 2981,
+    1931, 2315,  250, 1514, 1825, 3004, 1270, 4091, 2943, 1481, 2014, 2402,  245, 3652,  959, 2551,
+    1190,  367, 3864,  973,  259, 2055, 3949,    2, 3430,  648, 1744, 2382, 3299, 2127, 1548, 2304,
+    3199, 4026, 1172, 2881, 1463, 2554,  717, 3449,  358,  880, 1192, 3486, 1459, 3208,  374, 1965,
+     669, 2468,  444, 2202,   93, 2889, 3825, 3345, 2077, 4055,  781, 3688, 2834,  497, 2118,  282,
+    1104, 3611,  785, 3222, 1039, 3584, 2233,  603, 2560, 1124, 3740,  748, 3223, 1776, 2153, 3936,
+    3300, 1884, 1455, 2707, 3191,  731, 2941, 1446, 2253, 3066,  994,  146, 2899,  532, 3499, 1053,
+    2759, 2011,    3, 2164, 3600,  321, 4079, 2235, 2646, 1717, 3005, 2189,  721, 2687, 2311, 4016,
+    1284, 3594, 1826, 3121, 1409, 2319,  415, 1266,  629, 1682, 2260,  160, 1920, 1420, 3075, 3478,
+    2495, 1427, 2848, 2022, 3822,   65, 1545, 3412, 1784,  129, 3051, 2637, 1504,  499, 2772,   31,
+     821, 2373,  572, 3512, 1219, 1817, 3608,  373, 2716, 1236, 3755, 3450, 1891, 1352, 2616,  231,
+    1447,  642, 3751,  909, 3310, 1881, 1565, 1136, 3265,  536, 3660,  324, 3881, 1097, 1595, 3317,
+      75, 2903,  764, 3758, 1012, 3431, 1857, 2481, 2996, 3495, 2711, 1239, 3234, 3750,  969, 1727,
+     644, 3877,  178, 2400,  667, 2660,  911, 2858,  475, 3593, 1929, 1035, 4035, 3441, 1261, 3148,
+    1594, 2892, 4072, 2120,  102, 2536,  988, 2030, 3995,  470, 1671, 2529,  770, 3919, 2081, 3613,
+    1800, 3262, 2422, 1312, 2676,  664, 3052,  215, 3854, 2072, 1273, 2515, 1801, 3106,  468, 2158,
+     979, 2581, 1573, 2131,  203, 2696,  753, 3941,    7, 1478,  473, 3933,  700, 2286,   77, 2667,
+    3209, 1878, 1183, 3083, 1675, 3344, 1997, 3876, 2295, 1374,  645, 2172,  191, 2394,  736, 2065,
+    3567, 1033,  384, 3110, 1384, 3818, 3298, 1597,  746, 3246, 2134,  317, 1166, 3166,  414,  950,
+    3859,  303, 2989, 1688,  155, 3669, 2300, 2579,  739, 1635, 2880,    8, 3463,  827, 2830, 3739,
+    1898, 3494,  381, 4073, 3031, 1624, 3621, 1080, 2193, 3130,  944, 2459, 1589, 2936, 1175, 4067,
+     397, 2250, 3630,  500, 4018, 1344,  238, 1141, 3239, 2611, 3807, 3357, 2980, 1809, 3773, 2681,
+     247, 1925, 2609, 1645,  658, 2820,  337, 2313, 2908, 1320, 2656, 3654, 2934, 1587, 2280, 2812,
+    1230, 2125,  841, 3924, 3177, 2003,  970, 1387, 3532, 3168,  933, 4047, 2096, 1474, 2383,  214,
+    1379, 3150, 1160,  650, 2440, 1222,  395, 2864, 1720, 3803, 2008, 3421,  270, 3637, 2016, 1442,
+     782, 2985, 1592,  964, 2115, 2546, 2998,  710, 1697,   73,  930, 1568,  404, 1088, 1465,  579,
+    3906, 1267, 3315, 3703, 2264, 1829,  937, 3580,   63, 3897,  916, 1814,  202, 4029,  685, 3466,
+    2599, 1601,  517, 2371, 1181,  428, 4003, 2758,  135, 1913, 2395,  413, 1162, 3275,  588, 3970,
+     803, 2283, 2723, 1759, 3400, 1991, 3214, 2410,  674,  306, 1224, 2630, 1770,  576, 2405, 3260,
+    3524, 2509,   11, 2872, 3398,  399, 3661, 2218, 3983, 3119, 2031, 2484, 2824, 3581, 3217, 2113,
+    3007, 2381,  828,  425, 1147, 4008, 3085, 1467, 1986, 2428,  522, 3487, 1339, 2482, 1944,  100,
+    3115, 3794, 2789, 3372, 1838, 2927, 1602, 2156,  620, 3802, 1506, 2701, 3639, 1790, 2987, 2636,
+    1655, 3670,   40, 3811,  886,  253, 3973, 1404, 3570, 2811, 3288,  805, 3986, 3048,  975,  209,
+    1739, 1125, 3951, 1926, 1309,  852, 1830, 1400, 2668,  521, 1231, 3718,  762, 2257,   53,  976,
+    1613,  179, 1872, 3508, 2749,  211, 2483,  607, 3356, 1109, 3200, 2088, 2800,  854, 3280, 1040,
+    2256,  294, 1401,  963,   50, 3714,  791, 3407, 3009, 1091, 3325,  719, 2228,  139,  981, 3459,
+     361, 2034, 3101, 1301, 2887, 2154,  577, 1828,  995, 2288, 1496,   85, 2201, 1298, 2671, 3836,
+    2137,  649, 2750,  349, 3771, 2429, 3266,  161,  991, 3488, 2879,  316, 1798, 1342, 3843, 2654,
+    3281, 3975, 2524, 3120, 1582, 2128, 1256, 3663, 1747, 2839,  137, 1605, 3812,  400, 3648, 1726,
+     605, 3505, 2054, 3966, 2582, 2277, 1252,  256, 2491, 1773,  311, 2847, 3916, 1333, 2523, 2147,
+    1487, 1089,  631, 2572, 1604, 3448, 2500, 3103,  176, 3930, 1953, 2929, 3695, 1690,  360, 3163,
+    1412, 3626, 2282, 3054, 1599,  545, 2910, 3921, 2307, 1928, 1494, 4068, 2435, 2960,  416, 1941,
+     675, 1078, 1363,  508,  884, 3891, 2921,  354,  844, 4095, 2229,  683, 1187, 2339, 2886, 1285,
+    2685,  868, 3024, 1677,  656, 3206, 1866, 3612, 1403, 4030, 2076,  939, 1693, 3043,  611, 3866,
+    3193, 3606, 2209, 4032,  143, 1066, 3784, 1311, 2690,  761, 3339,  503, 1000, 3388,  672, 1916,
+    2526,  115, 1272,  931, 3490, 2026, 1150, 1678,  728, 3182,  111,  927, 3348,  647, 3485, 1456,
+    3696, 2931, 2287, 3642, 1810,    4, 3274, 2020, 2569, 1291, 3424, 2703, 3147, 1977,   13, 4041,
+    1769, 2334,  156, 1186, 3650,  437, 2677,  890, 2920,  591, 2401, 3677,  243, 3484, 1946,   90,
+     835, 2746,  398, 1772, 3146,  737, 2033,  457, 3595, 1751, 2446, 1385, 2765, 2341, 4022, 2909,
+     877, 3346, 1845, 3928,  200, 2628, 3379,  390, 2474, 3805, 2700, 2108, 1674, 1188, 2232, 2601,
+      89, 2057,  338, 3312, 2736, 2378, 1079, 1540, 3760,  451, 1811,  258, 3910,  826, 1471, 3207,
+    1128, 3852, 3405, 2731, 2132, 1525, 3857, 2056,   66, 3444, 1153, 3134, 1480, 2587, 1082, 2913,
+    1667, 1249, 2453, 3523, 1406, 2738, 2354, 1554, 3040, 1041,  265, 3540, 1849,   52, 1527, 1152,
+    3708,  549, 2965, 2461, 1483,  830, 3831, 2853, 1428, 1059,  493, 3628, 3097,  261, 3958, 3158,
+    1019, 1644, 4062,  938, 1419,  529, 3531, 2827,  730, 3123, 2517, 1043, 1665, 3533, 2577,  353,
+    2009,  478, 1422,  792, 3124,  269, 1115, 3301, 1571, 2644, 1839,  394, 2212,  694, 4064, 2336,
+    3685, 3068,  684, 1990,  304, 3848, 3302,   22, 4057, 2600, 2146, 3809,  811, 3232, 2215,  336,
+    2632, 1343, 2123,  369, 3216, 2271, 1779,  131, 2140, 3319, 1851, 1306, 2486,  864, 1823,  539,
+    3558, 2493,  646, 3099, 2121, 3875, 1780,  232, 2165, 1411, 3480, 2090, 2922,  613, 2227, 3748,
+    2815, 3176, 2418, 1842, 4065, 2492, 2946,  708, 2327, 3974,  845, 3742, 2762, 3387, 1357,  465,
+    2068,  222, 3964, 1119, 2966,  798, 1197, 1924,  632, 1426, 2973,  471, 1288, 2867, 3842, 1947,
+    3454, 1676, 4054, 1083, 3530,  555, 1244, 3102, 3667,  705, 2819,   37, 3768, 2916, 1390, 2157,
+    2868, 1302, 1832, 2612,  119, 1032, 2955, 2419, 4002,  872,  391, 3832,  126, 1354, 3382,  945,
+    1603,  703, 3681,   41, 1279,  571, 1788, 3536, 1324,  171, 3026, 1251, 1996,   71, 1741, 3233,
+     919, 1461, 3285, 2557, 2160, 1633, 3618, 2302, 3235,  903, 1964, 3425, 1707, 2499,  621,  967,
+    3000,  165,  760, 2722, 1880, 2930, 3952,  958, 2540, 1508, 3997, 2028,  606, 2318, 3491,  366,
+    3860,  180, 3241, 3801, 1569, 3384,  618, 1238, 3178, 1910, 2748, 1610, 2393, 3081, 1848,  188,
+    3977, 2192, 1068, 2850, 3389, 2258, 3806,  346, 2844, 2179, 1698,  535, 3569, 1031, 2894, 2452,
+    3644, 2831, 1778,  132, 3458,  504, 2624,  267, 3745, 2740,   98, 3927, 1056,  224, 3551, 1457,
+    2245, 3284, 2471, 3704,   14, 1529, 2312,  251, 1934,  406, 1121, 3409, 1622, 3136, 1095, 1761,
+     833, 2357, 1126,  558, 2173, 2710, 3719, 1702,   91, 3609, 1054, 3306,  715, 1155, 3647, 2510,
+    1341,  383, 3242, 1968, 1617,  856, 2634, 1510, 1022, 3279, 3912, 2558, 3082, 2166, 3960,  666,
+     348, 2252, 1067,  745, 3913, 1382, 3060, 1802, 1131, 1584, 2389, 3092, 2169, 2802, 1885, 3889,
+     408, 1114, 1765, 1336, 2152,  808, 3428, 2732, 3610, 3070, 2222, 2633,  917,  162, 4042, 2585,
+    3353, 1998, 3615, 2983, 1371,  277, 1979,  799, 2926, 2289,  516, 2622, 4083, 2141,  459, 2900,
+    1803, 3544, 2665,  546, 3956,  154, 3096, 2101, 3671,  686,  244, 1440,  867,  302, 1612, 1212,
+    1976, 3856, 3332, 2447, 2876, 2021,  892, 4014,  385, 3370,  670, 1349,  450, 3322,  774, 2596,
+    2932, 3658,  598, 2808, 3898, 3188,  491, 1283, 1673,  668, 3861,  320, 2890, 1980, 1399,  570,
+    2837, 1517,  379, 2534,  866, 4049, 3228, 2494, 1484, 3885, 1289, 1813,   43, 1566, 3347,  778,
+    2417,  120, 1234, 2306, 1026, 3518, 1247,  446, 1743, 2727, 2350, 1927, 3779, 3303, 2489, 3538,
+    2744, 1430,  276, 1711, 1203,   62, 3521, 2408, 2891, 2046, 3711, 1815, 4090, 1145, 1658,   61,
+    1310, 2049, 3416,  279, 1013, 2432, 1972, 4087,  997, 2532, 1793, 1319, 3649, 3218, 2323, 3734,
+      76, 1051, 3934, 1835, 3088, 2278, 1144,  494, 3394,  263, 2100, 3571, 3135, 2737, 1118, 3892,
+    3037, 1555, 3712, 3180, 1873, 2817, 2433, 3829, 3202,  929, 3408, 1151, 2860,  568, 1791,   44,
+     902, 3027,  625, 3730, 3197, 2240,  585, 1636, 1262,  837, 2580,  123, 2961, 2496, 3468, 2270,
+    3965,  850, 2518, 1875, 3039, 1488,  205, 2816, 3338,   51, 3510, 2122,  448, 1149,  765, 1638,
+    3013, 2205, 3271,  661, 1632,   42, 3586, 1852, 2658, 1030, 2976,  634,  947, 2349,  255, 2066,
+     492,  954, 2776,  638,  219, 1537,  727, 2013,   23, 1572, 4019,  194, 2144, 1405, 3105, 4069,
+    2124, 3483, 2549, 1912,  824, 3878, 2697, 3318,  322, 3057, 3527, 1505,  934, 1950,  593, 3073,
+    1570,  460, 3226, 1206, 3772,  689, 3575, 2196,  626, 1450, 2986,  834, 2768, 3946, 2562, 1906,
+    3504, 1292,  290, 2666, 3804, 1325, 2840,  772, 3761, 2261, 1512, 3944, 1777, 3440, 1396, 3577,
+    2617, 3845, 1736, 2188, 4070, 3377, 2992, 1081, 2314, 2933,  604, 2527, 3629,  822, 2662, 1130,
+     502, 1607, 1250, 2863,  377, 1497, 1010, 1874, 3988, 2161,  553, 2343, 3833,  309, 3641, 1006,
+    2739, 3582, 1709,  107, 2296, 2673, 1746, 1092, 3145, 1961, 3830, 2374, 1576,  124, 3264,  376,
+     695, 2465, 3617, 2036,  920, 3249, 2136, 1552,  142, 3270,  479, 2584,  310, 2849,  693, 1855,
+    1277,   56, 3291,  879, 2480, 1315,  297, 3899, 3461, 1330, 1914, 3154, 1651,  345, 3367, 1894,
+    2390, 3759,  249, 2203, 3079, 3550, 2472,  138, 2760, 1069, 1695, 3172, 1220, 2791, 2084, 1334,
+     201, 2185, 2951, 3945,  936, 3295,  305, 3967, 2506,  260, 1157,  524, 3476, 2053, 1365, 2846,
+    4080, 1002, 1511, 2957,  183, 2501,  531, 3984, 2895, 1274, 1989, 3720, 1182, 2143, 4039, 3161,
+    2441, 2093, 1460, 3572,  511, 1963, 2639, 1672,  453, 2741,  900, 3855, 1198, 2298, 3926,  170,
+    2948, 3296,  946, 3979, 1169, 2043,  640, 3777, 1415, 3640,  223, 2641,  725, 3320, 1639, 4033,
+    2553,  819, 1416,  501, 2078, 1276, 2896, 1513,  859, 3619, 2801, 1804, 3028,  914, 3733, 2191,
+    1716, 3125,  464, 1862, 3918, 1142, 3537, 1846,  974, 2470, 3462,  829, 3056, 1616,  229, 1009,
+     643, 3046,  329, 2742, 1027, 3786, 3244,  796, 2086, 3665,  173, 2442,  538, 3012, 1760, 1052,
+    1434,  575, 2640, 1652,   15, 3393, 1745, 2935,  815, 2070, 3401, 1818, 3909,   16, 2409,  542,
+    3169, 3717, 1889, 3403, 2586, 3723,  554, 1942, 3335, 2211, 1359, 4027,  184, 2469,  600, 1154,
+       5, 2293, 3762,  795, 2360, 1590, 2718,  268, 3144,  512, 1666,    1, 2347, 3399, 2688, 3679,
+    3341, 1648, 3996, 2242, 3017, 1503,   97, 2337, 1185, 3078, 1775, 3391, 1485, 3588,  759, 2832,
+    3693, 1978, 2301, 3599, 2803,  889, 2369,  350, 3267, 2513,  509, 1353, 2226,  894, 2972, 1170,
+    1689,  285, 2786, 1134,   48, 1692, 2358, 3022,  125,  671, 2592, 1001, 3287, 1629, 2784, 3635,
+    3323, 2650, 1328, 3465, 2995,  615, 3381, 1346, 2110, 3662, 2757, 3959, 1093,  469, 1417, 1948,
+     149, 2570,  789, 1229,  423, 1876, 3552, 2807, 4045,  665, 2576, 1008, 2225,  332, 1943, 2485,
+      88, 3142,  802,  419, 1429, 3190, 3869, 1264, 1642, 4037, 1015, 3044, 3590, 1532, 3791, 2058,
+    3467, 2325,  663, 3061, 4053,  801, 3482, 1105, 3792, 1735, 3568,  452, 1974, 3840, 1316,  382,
+    1954,  966,  403, 2103,  112, 1935,  918, 4052, 2414,  732, 1937, 1445, 2991, 2085, 3870,  932,
+    1347, 3729, 1981, 3259, 3887, 2507,  935, 1367,  362, 1562, 3767,   34, 2797, 4025, 3313, 1111,
+    3817, 1681, 1217, 4056, 2566, 1911,  562, 2239,  150, 2779, 1993,  272, 2437,  495, 2684,  133,
+     840, 3863, 1290, 1975, 2279, 1489, 2678,  430, 2075, 2823, 1408, 3175, 2272,  773, 2404, 3076,
+    1579, 3999, 2875, 1466, 3721, 3192, 2568, 1628,  136, 1208, 3247,  239, 3583,  704, 2416, 2944,
+    2180,  537, 2733,   17, 1553,  589, 3162, 1955, 3434, 2142, 2970, 1840, 1223,  581, 1549, 2338,
+     380, 2680, 3436, 2148,  220, 1087, 2888, 3554, 3152,  707, 3763, 1242, 3438, 1833, 1074, 3023,
+    1468, 2545, 3263,  341, 3627,  187, 3091, 1275, 3994,  838, 2443,  318, 1193, 2956,  174, 3534,
+     722, 2504, 3273,  574, 2274, 1178,  433, 3475, 2940, 3724, 2502,  912, 2661, 1533,  315, 3516,
+    3210, 1664,  987, 3535, 2116, 2878, 3795,  181, 2715, 1077,  463, 3257, 3690, 2539, 3117,  865,
+    1890, 3015,  525, 1543, 3269, 3738, 1712,  848, 1436, 2356, 1737, 2706,  777, 3203, 4084, 2194,
+    3414,  393, 1728, 2751, 1024, 1812, 2399, 3374, 1640,   24, 3311, 3725, 1680, 4036, 1864, 1108,
+    2204,   82, 1789,  901, 3942, 2787, 1820,  817, 2052,  526, 1724, 2216, 3340, 4075, 1836, 1076,
+     169, 3972, 2997, 2380, 1258,  876, 1714, 2397,  716, 3932, 2308, 1423,  779, 2079,  182, 3957,
+    1314, 3656, 1028, 2370,  680, 2734,   81, 2089, 3992,  445, 3503,   54, 2234, 1608,  287,  653,
+    1899, 1110, 3976,  594, 3181, 3837,  775,  486, 2238, 2942, 1879,  960, 2726,  496, 2521, 3160,
+    3871, 1322, 3632, 2575, 1535,  189, 3112, 3821, 1366, 2841, 3905,  288, 1254,  584, 2869, 2487,
+    1364,  720, 1897,  314, 3872,  412, 3579, 1294, 3001, 1611, 3506,  246, 2907, 1713, 3451, 2783,
+    2112,  109, 2605, 3915, 1917, 1372, 3111, 2530, 1164, 2857,  961, 3064, 3868, 1282, 2862, 2525,
+    3699, 2953, 2379, 1598, 2105, 1243, 2647, 3701, 1099, 3497,  662, 2321, 1414, 3477,  813, 1538,
+     334, 2713, 2035,  387, 3481, 2187, 1060, 2376,   33, 3418, 1036, 1609, 3087, 2119,  878, 3680,
+    2299, 2804, 3456, 1502, 3140, 2659, 2155, 3307,   60, 1939,  986, 2457, 3896, 1146,  513, 1479,
+     735, 3395, 1704,  308, 3354,  983, 3816,  343, 3293, 1982, 1501, 2450,  541, 2012, 3559,  913,
+    1413,   29,  836, 3432,  330, 2994,  104, 1932, 1437, 2682,  241, 3776, 3089,   96, 2138, 3676,
+    2969,  677, 3334,  977, 2901,  624, 3668, 1575, 2619,  755, 2263, 2694, 3633,   74, 3316, 1684,
+    3834,  227, 1038, 2497,  750, 1782, 1064,  573, 4071, 2675, 3205,  483, 2023, 3248, 2555, 3728,
+    2305, 3036, 1259, 2829, 2208,  533, 2333, 1764,  698, 3900,  312, 3614, 1168, 3240,  196, 1757,
+    3294, 2170, 3797, 2724, 1475, 3917, 2367, 3213,  809, 4061, 1606, 1970, 1180, 2766, 1017, 1834,
+    2344, 1232, 1722, 4076, 1397, 1918, 3254,  462, 4023, 1807, 3225,  530, 1900, 1329, 2544,  506,
+    2004, 3194, 2184, 4011,  114, 3691, 2917, 1539, 2290,  776, 1351, 3775, 1531,  896,   69, 1883,
+    1048,  421, 4092,  853, 3651, 1578, 2781, 3565, 1355, 2635, 2162, 1631, 2686,  751, 2359, 3847,
+    2653,  630, 1148, 1999,  474,  998, 1691, 3529,  402, 2181, 3149,  583, 2477, 3990, 3245,  454,
+    3810,   49, 3072, 2439,  230, 2663, 1215, 2145, 2893, 1280,  233, 3735,  915, 3950, 3021, 1120,
+    1418,  565, 1637, 2851, 1263, 2024, 3368,  331, 3726, 1819, 2925,  207, 2269, 2971, 3969, 2712,
+    3492, 1641, 2462, 1952,   18, 3069, 1072,  237, 3189,  921, 3402,  144, 4024, 3034, 1486, 1075,
+     396, 3025, 1625, 3198, 3631, 2276,  652, 2594, 1265, 2870,  978, 3411,  275, 1662,  744, 1451,
+    2614, 3511,  846, 2067, 3464,  793, 3867,  127,  962, 3545, 2508, 1507, 2785, 2195,  281, 3489,
+    2745, 3731,  873, 3553,  484, 2424,  863, 2631, 1156, 3243,  599, 2543, 3547, 1755,  713, 1368,
+     319, 3104,  633, 3328, 1377, 2117, 3722, 2467, 1930,  518, 2897, 1084, 1877,  485, 2176, 3498,
+    1945, 4046, 2396,   84, 2792, 1345, 3962, 3063,  118, 1768, 3886, 1392, 2241, 3560, 2019, 2902,
+    1086, 2199, 1591,  351, 2874, 1785, 3109, 2375, 1723, 3050, 2064,  651, 3396, 1721,  754, 2427,
+    1792,    9, 2361, 1909, 3253, 1495, 3980, 1753,   94, 3597, 1995,  952, 1246,  375, 3290, 2372,
+    1962, 3841, 1179, 2623, 3893,  439,  767, 1499, 3998, 2292, 1701, 3754, 2516, 3215,  887, 2882,
+     221, 1321,  702, 3378,  895, 1799,  325, 2037, 3700, 2436,  467, 2670,  842, 3014,  195, 3883,
+     388, 3129, 3963, 1313, 3709,  447, 1444, 3653,  714,  359, 3989, 1199,   83, 2939, 1070, 4093,
+    1281, 3326, 2963, 1057,  204, 2809,  578, 3114, 2353, 1438, 2752, 4001, 3067, 2149, 3798,  999,
+    2835,  103, 2182,  893, 1663, 2769, 3439, 3008,   99, 1184, 3549,  690, 1389,   39, 3935, 1660,
+    2535, 3585, 2091, 1574, 3782, 2519, 3297, 1071, 1462,  784, 3337, 1902, 3753, 1139, 2533, 1715,
+    2387,  627, 1886, 2695, 1049, 2221, 2598, 1117, 3343, 2767, 1588, 2466, 3757, 1988, 3185,  435,
+    2139,  687, 1524, 3943, 2236, 3576, 1245, 2069, 3785,  768,  426, 1654,   26, 2625, 1443,  510,
+    3625, 1705, 2975, 3566,  228, 2267, 1860,  980, 2615, 3255,  326, 2761, 2107, 3373, 2320, 1189,
+     561, 3171,  989, 2968,  488, 2150,  692, 2754, 3525, 2285, 2859,  134, 1614,  544, 3230, 1296,
+    3413,  928, 3603,  198, 3330,  660, 4020,   19, 1936, 2275,  922, 3452,  563, 1361, 2348, 3620,
+    2898, 3790, 2514,  427, 1806,  810, 2651,  278, 1055, 2979, 3422, 2060, 3555,  807, 1858, 3221,
+    2451, 1304,  559, 3183, 1241, 3954,  580, 1410, 3793, 2010, 1561, 3095, 1011, 1827,  364, 3783,
+    2689, 1808, 3907,  148, 2643, 1331, 4094,   25, 1643,  436, 1237, 4012, 3093, 2214, 3692,   32,
+    2071, 2919, 1557, 2488, 2002, 3038, 1708, 2845, 1269, 3828,  210, 3010, 1783, 2755,  193,  957,
+    1893,  128, 1210, 3473, 3137, 1424, 3890, 3283, 1627, 2565, 1348, 2426, 1159, 3939, 2777,  213,
+     925, 4021, 1938, 2415,  839, 2884, 3327, 2448,  409,  787, 2322, 4077,  592, 3655, 2805,  823,
+    1393,  313, 2326, 1176, 3638, 1754, 3141, 1987, 3736, 2982, 2111,  943, 2552, 1407,  771, 2725,
+    4074,  458, 1191, 3846,  843, 1370,  339, 3479,  758, 3179, 1477, 2159,  847, 3982, 3278, 1620,
+    3542, 2674, 2092,  655, 2855,   46, 2324, 1847,  498, 4059,  147,  688, 3164,  370, 2254, 3406,
+    1559, 2822,  140, 3707, 1530, 2063,   36, 1679, 2928, 3383, 1221,   95, 2505, 1500, 3211, 1748,
+    3442, 2915, 1960, 3371,  595, 2861,  806, 1133, 2476,  723, 3376, 1742,  212, 3423, 1971, 1073,
+    1683, 2366, 3167,  108, 3375, 2303, 3774, 1983, 2411,  490, 2620, 3664,  307, 1218, 2537,  543,
+    3045,  858, 4050, 1696, 2444, 1100, 3493,  734, 3086, 2183, 3672, 1903, 2854, 1668, 1205,  601,
+    3778, 2151, 1096, 3355,  480, 2705, 3546, 1047, 3826, 1856, 2743, 3522, 2001, 1122,  167, 2197,
+     548, 4031,  874, 1519, 2498,  298, 2259, 3496,  254, 1394, 3814,  557, 2780, 3901,  429, 2999,
+    3502,  712, 2048, 2799, 1659,  540, 2698, 1020, 1580, 4085, 1112, 1919, 3077, 2249, 3796, 1458,
+    2330,  273, 1300, 3366,  477, 3781, 2104, 1308, 2774, 1023, 1473, 3365,  888, 3827, 2603, 1984,
+    3074,  333, 2613, 1767,  766, 4048, 1297, 2237,  659,  327, 1469,  855, 3058, 3888, 2595, 3573,
+    1278, 2355,   68, 3151, 3743, 1271, 3920, 1593, 3227, 2649, 1907, 3131, 1204, 2243, 1541, 2520,
+     240, 1356, 3674,  955, 4007, 1227, 3059,  262, 3360, 2821,   70, 3517,  709, 1699,  368, 1063,
+    3706, 3156, 2717, 2015,  942, 2906, 1560,  206, 3587,  365, 2652,  507, 2368,   55, 3556, 1433,
+     825, 3513, 1369, 3108, 2362, 1915,  248, 3126, 2538, 3289, 3987, 2246,  284,  718, 1822,  968,
+    3094, 1647, 2790, 2042,  992, 1844, 2949,  482, 2094, 1029,   58, 2398,  706, 3634,  904, 3219,
+    3961, 1861, 2645,  352, 2425, 1882, 3557, 2186,  691, 1756, 2309, 1402, 2573, 3352, 2865, 2025,
+    1771,  724, 1526, 3874,  106, 3236, 2578, 3904, 1969, 3277, 1719, 3971, 2007, 1046, 2954,  431,
+    2420, 1869, 3908,   78, 1004, 3385, 2806, 1630,  951, 2061, 1174, 2672, 1626, 3363, 2905,  235,
+    3835,  476, 3675,  657, 3359,  130, 2542,  882, 3689, 2775, 4009, 1619, 3350, 1850,  145, 2135,
+    1103,  586, 3127, 1556, 3314,   86,  881, 1432, 3922, 3201,  996, 3838,  442,  926, 4017,   20,
+    2588, 3563,  401, 2479, 1195, 1824,  534,  871, 2385, 1228,  711, 3018, 1350, 3333, 1649, 4088,
+    2836, 1143,  616, 2729, 3666, 1388,  410, 3925, 3501,   59, 2964,  550, 3622, 2328, 1332, 2018,
+    2463, 1102, 2610, 1439, 2248, 4044, 1327, 3390, 1752,  608, 1362, 2950,  344, 2597, 3727, 1493,
+    2866, 2291, 3602,  814, 2126, 3813, 2606, 2904,  432, 2458,  175, 1863, 3032, 1520, 2413, 1225,
+    3308,  972, 2133, 3030, 3683, 2297, 3469, 1448, 3118,  283, 3673, 2548,  208, 2097,  636, 2265,
+     172, 3349, 2040, 1581, 2175,  726, 2430, 1994,  628, 1558, 3756, 1908,  982,  372, 4063,  740,
+    3455, 1738, 3250,  286, 3055,  749, 1985, 2871,  166, 2340, 3598,  949, 2206, 1248, 3041,  505,
+    3858,   47, 1375, 2947,  515, 1200, 1730, 3607, 2062, 1293, 3686, 2721, 3417, 2106,  612, 2958,
+];
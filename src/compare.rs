@@ -0,0 +1,225 @@
+/*
+ * Image comparison metrics, for checking renders against a golden image.
+ *
+ * All of them expect both images to have the same size and values in 0..1
+ * (e.g. what `Image::from_ppm` produces).
+ */
+
+use crate::image::Image;
+use crate::primitives::{Float, Vec3};
+
+fn check_sizes(lhs: &Image, rhs: &Image) {
+    assert!(
+        lhs.width == rhs.width && lhs.height == rhs.height,
+        "Can't compare a {}x{} image against a {}x{} one",
+        lhs.width, lhs.height, rhs.width, rhs.height,
+    );
+}
+
+fn luminance(c: Vec3) -> Float {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+// Root mean squared error over all channels
+pub fn rmse(lhs: &Image, rhs: &Image) -> Float {
+    check_sizes(lhs, rhs);
+    let sum_squared: f64 = lhs.pixels.iter()
+        .zip(rhs.pixels.iter())
+        .map(|(a, b)| (*a - *b).length_squared() as f64)
+        .sum();
+    (sum_squared / (lhs.pixels.len() * 3) as f64).sqrt() as Float
+}
+
+// Peak signal to noise ratio in decibels. Identical images give infinity.
+pub fn psnr(lhs: &Image, rhs: &Image) -> Float {
+    let error = rmse(lhs, rhs);
+    // the peak value is 1.0
+    -20.0 * error.log10()
+}
+
+// Separable gaussian blur (sigma 1.5, 11 taps) of a single channel, clamped at the edges
+fn gaussian_blur(values: &[Float], width: usize, height: usize) -> Vec<Float> {
+    const RADIUS: i32 = 5;
+    const SIGMA: Float = 1.5;
+    let weights: Vec<Float> = (-RADIUS..=RADIUS)
+        .map(|i| (-(i * i) as Float / (2.0 * SIGMA * SIGMA)).exp())
+        .collect();
+    let total: Float = weights.iter().sum();
+
+    let blur = |src: &[Float], horizontal: bool| -> Vec<Float> {
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                weights.iter().enumerate().map(|(idx, weight)| {
+                    let offset = idx as i32 - RADIUS;
+                    let (sx, sy) = if horizontal {
+                        ((x as i32 + offset).clamp(0, width as i32 - 1) as usize, y)
+                    } else {
+                        (x, (y as i32 + offset).clamp(0, height as i32 - 1) as usize)
+                    };
+                    src[sy * width + sx] * weight
+                }).sum::<Float>() / total
+            })
+            .collect()
+    };
+    blur(&blur(values, true), false)
+}
+
+/*
+ * Mean structural similarity index of the luminance, see "Image Quality
+ * Assessment: From Error Visibility to Structural Similarity" (Wang et al. 2004).
+ *
+ * 1.0 means identical, values near 0.0 mean unrelated images.
+ */
+pub fn ssim(lhs: &Image, rhs: &Image) -> Float {
+    check_sizes(lhs, rhs);
+    let (width, height) = (lhs.width, lhs.height);
+    let x: Vec<Float> = lhs.pixels.iter().map(|c| luminance(*c)).collect();
+    let y: Vec<Float> = rhs.pixels.iter().map(|c| luminance(*c)).collect();
+    let product = |a: &[Float], b: &[Float]| -> Vec<Float> {
+        a.iter().zip(b.iter()).map(|(a, b)| a * b).collect()
+    };
+
+    let mu_x = gaussian_blur(&x, width, height);
+    let mu_y = gaussian_blur(&y, width, height);
+    let xx = gaussian_blur(&product(&x, &x), width, height);
+    let yy = gaussian_blur(&product(&y, &y), width, height);
+    let xy = gaussian_blur(&product(&x, &y), width, height);
+
+    let c1 = (0.01 as Float * 1.0).powi(2);
+    let c2 = (0.03 as Float * 1.0).powi(2);
+    let total: f64 = (0..x.len()).map(|i| {
+        let var_x = xx[i] - mu_x[i] * mu_x[i];
+        let var_y = yy[i] - mu_y[i] * mu_y[i];
+        let covar = xy[i] - mu_x[i] * mu_y[i];
+        let numerator = (2.0 * mu_x[i] * mu_y[i] + c1) * (2.0 * covar + c2);
+        let denominator = (mu_x[i] * mu_x[i] + mu_y[i] * mu_y[i] + c1) * (var_x + var_y + c2);
+        (numerator / denominator) as f64
+    }).sum();
+    (total / x.len() as f64) as Float
+}
+
+// Black -> red -> yellow -> white color ramp for `t` in 0..1
+pub fn heat_color(t: Float) -> Vec3 {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    Vec3::new(t.min(1.0), (t - 1.0).clamp(0.0, 1.0), (t - 2.0).clamp(0.0, 1.0))
+}
+
+/*
+ * Per-pixel error visualization.
+ *
+ * Goes black -> red -> yellow -> white as the error grows, reaching white
+ * at `max_error` (per channel RMS error, same units as the images).
+ */
+pub fn error_heatmap(lhs: &Image, rhs: &Image, max_error: Float) -> Image {
+    check_sizes(lhs, rhs);
+    let pixels = lhs.pixels.iter()
+        .zip(rhs.pixels.iter())
+        .map(|(a, b)| {
+            let error = (*a - *b).length() / (3.0 as Float).sqrt();
+            heat_color(error / max_error)
+        })
+        .collect();
+    Image {
+        width: lhs.width,
+        height: lhs.height,
+        pixels,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aov::Aov;
+    use crate::output::OutputSettings;
+    use crate::primitives::Vec2i;
+    use crate::random;
+    use crate::renderer::{render_image, RenderProperties};
+    use crate::scene::Scene;
+
+    fn golden() -> Image {
+        Image::from_ppm(include_bytes!("small.ppm")).unwrap()
+    }
+
+    #[test]
+    fn test_identical() {
+        let image = golden();
+        assert_eq!(rmse(&image, &image), 0.0);
+        assert_eq!(psnr(&image, &image), Float::INFINITY);
+        assert!((ssim(&image, &image) - 1.0).abs() < 1e-4);
+        let heatmap = error_heatmap(&image, &image, 0.1);
+        assert!(heatmap.pixels.iter().all(|px| *px == Vec3::zero()));
+    }
+
+    #[test]
+    fn test_constant_offset() {
+        let image = golden();
+        let mut brighter = image.clone();
+        brighter.pixels.iter_mut().for_each(|px| *px += Vec3::ones() * 0.1);
+        assert!((rmse(&image, &brighter) - 0.1).abs() < 1e-4);
+        assert!((psnr(&image, &brighter) - 20.0).abs() < 0.01);
+        // structure is untouched, so SSIM stays high
+        assert!(ssim(&image, &brighter) > 0.9);
+    }
+
+    #[test]
+    fn test_noise_lowers_ssim() {
+        let image = golden();
+        let mut noisy = image.clone();
+        noisy.pixels.iter_mut().enumerate().for_each(|(idx, px)| {
+            // deterministic salt and pepper
+            if idx % 7 == 0 {
+                *px = if idx % 2 == 0 { Vec3::zero() } else { Vec3::ones() };
+            }
+        });
+        let score = ssim(&image, &noisy);
+        assert!(score < 0.9 && score > 0.0, "SSIM was {}", score);
+    }
+
+    #[test]
+    fn test_heatmap_saturates() {
+        let black = Image::new(2, 1);
+        let mut other = Image::new(2, 1);
+        other.pixels[0] = Vec3::ones();
+        other.pixels[1] = Vec3::ones() * 0.05;
+        let heatmap = error_heatmap(&black, &other, 0.5);
+        assert_eq!(heatmap.pixels[0], Vec3::ones());
+        assert!(heatmap.pixels[1].x > 0.0 && heatmap.pixels[1].y == 0.0);
+    }
+
+    #[test]
+    fn test_render_matches_golden() {
+        /*
+         * A converged render of the default scene, from
+         * `rustpt --seed 1 --width 48 --samples 2048 src/golden.ppm`. Only
+         * regenerate it when the image is meant to change (the scene, the
+         * shading), not when the noise does: this render has far fewer
+         * samples, so it only has to be close to it, not the same.
+         */
+        let golden = Image::from_ppm(include_bytes!("golden.ppm")).unwrap();
+        let img_size = Vec2i::new(48, 32);
+        random::seed(1);
+        let scene = Scene::random_scene(1.5);
+        let properties = RenderProperties {
+            samples: 32,
+            ..Default::default()
+        };
+        let output = OutputSettings::default();
+        let render = Image {
+            width: golden.width,
+            height: golden.height,
+            pixels: render_image(img_size, &scene, &properties)[&Aov::Color]
+                .iter()
+                .map(|px| output.display_color(*px))
+                .collect(),
+        };
+        // about 35 dB with these samples and 32 dB with purely random ones, a different scene is below 16
+        let (psnr, ssim) = (psnr(&render, &golden), ssim(&render, &golden));
+        assert!(psnr > 30.0 && ssim > 0.95, "PSNR {} SSIM {}", psnr, ssim);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_size_mismatch() {
+        rmse(&Image::new(2, 2), &Image::new(2, 3));
+    }
+}
@@ -0,0 +1,311 @@
+/*
+ * Mouse and touch camera controls for the canvas.
+ *
+ *  - left drag / one finger: orbit around the target
+ *  - right or shift drag / two fingers: pan
+ *  - wheel / pinch: zoom
+ *  - click / tap: focus on whatever is under the pointer
+ *
+ * The event listeners only record what happened in `Controls`, the renderer
+ * picks it up on its next step (it owns the scene, which focusing needs).
+ */
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use web_sys::{Event, HtmlCanvasElement, MouseEvent, TouchEvent, WheelEvent};
+
+use crate::orbit::OrbitCamera;
+use crate::primitives::{Float, Vec2f, consts};
+
+// radians per canvas width dragged
+const ORBIT_SPEED: Float = 2.0 * consts::PI;
+// zoom factor per wheel pixel, in the exponent
+const ZOOM_SPEED: Float = 0.002;
+// how far (CSS pixels) the pointer can move and still count as a click
+const CLICK_SLOP: Float = 4.0;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DragMode {
+    Orbit,
+    Pan,
+}
+
+#[derive(Copy, Clone)]
+struct Drag {
+    mode: DragMode,
+    start: Vec2f,
+    last: Vec2f,
+    moved: bool, // past the click slop, it's not going to be a click anymore
+}
+
+pub struct Controls {
+    pub view: OrbitCamera,
+    pub changed: bool, // the camera needs rebuilding and the accumulation restarting
+    pub focus_request: Option<Vec2f>, // image position (s, t) to focus on
+    drag: Option<Drag>,
+    pinch: Option<Float>, // distance between the two fingers
+}
+
+impl Controls {
+    pub fn new(view: OrbitCamera) -> Controls {
+        Controls {
+            view,
+            changed: false,
+            focus_request: None,
+            drag: None,
+            pinch: None,
+        }
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some_and(|drag| drag.moved)
+    }
+
+    // Positions are in CSS pixels from the top left corner of the canvas
+    pub fn pointer_down(&mut self, position: Vec2f, mode: DragMode) {
+        self.drag = Some(Drag {
+            mode,
+            start: position,
+            last: position,
+            moved: false,
+        });
+    }
+
+    // `size` is the CSS size of the canvas
+    pub fn pointer_move(&mut self, position: Vec2f, size: Vec2f) {
+        let Some(drag) = self.drag.as_mut() else {
+            return;
+        };
+        let from_start = Vec2f::new(position.x - drag.start.x, position.y - drag.start.y);
+        if !drag.moved && from_start.x.hypot(from_start.y) < CLICK_SLOP {
+            return;
+        }
+        drag.moved = true;
+        let delta = Vec2f::new(position.x - drag.last.x, position.y - drag.last.y);
+        drag.last = position;
+        match drag.mode {
+            // dragging right turns the scene right, so the camera goes left
+            DragMode::Orbit => self.view.orbit(
+                -delta.x / size.x * ORBIT_SPEED,
+                delta.y / size.x * ORBIT_SPEED,
+            ),
+            // the scene follows the pointer
+            DragMode::Pan => self.view.pan(-delta.x / size.y, delta.y / size.y),
+        }
+        self.changed = true;
+    }
+
+    // A press and release that didn't move is a click, and asks to focus there
+    pub fn pointer_up(&mut self, size: Vec2f) {
+        if let Some(drag) = self.drag.take() {
+            if !drag.moved {
+                self.focus_request = Some(Vec2f::new(drag.start.x / size.x, 1.0 - drag.start.y / size.y));
+            }
+            // one more change, so the accumulation restarts at full resolution
+            self.changed = true;
+        }
+    }
+
+    // The pointer left the canvas, no click
+    pub fn pointer_cancel(&mut self) {
+        if self.drag.take().is_some_and(|drag| drag.moved) {
+            self.changed = true;
+        }
+    }
+
+    // Wheel pixels, positive zooms out
+    pub fn wheel(&mut self, delta: Float) {
+        self.view.zoom((delta * ZOOM_SPEED).exp());
+        self.changed = true;
+    }
+
+    // Distance between the two fingers of a pinch, `None` once it's over
+    pub fn pinch(&mut self, distance: Option<Float>) {
+        if let (Some(last), Some(distance)) = (self.pinch, distance) {
+            if distance > 0.0 {
+                self.view.zoom(last / distance);
+                self.changed = true;
+            }
+        }
+        self.pinch = distance;
+    }
+}
+
+// event name and handler, to remove it with
+type Listener = (&'static str, Closure<dyn FnMut(Event)>);
+
+/*
+ * The listeners attached to a canvas, removed again when dropped (so the
+ * canvas never calls into a freed closure).
+ */
+pub struct Listeners {
+    canvas: HtmlCanvasElement,
+    closures: Vec<Listener>,
+}
+
+fn canvas_size(canvas: &HtmlCanvasElement) -> Vec2f {
+    Vec2f::new(canvas.client_width().max(1) as Float, canvas.client_height().max(1) as Float)
+}
+
+// Position of the mouse over the canvas
+fn mouse_position(event: &MouseEvent) -> Vec2f {
+    Vec2f::new(event.offset_x() as Float, event.offset_y() as Float)
+}
+
+// Center of the touches over the canvas, and how far apart the first two are
+fn touch_position(canvas: &HtmlCanvasElement, event: &TouchEvent) -> Option<(Vec2f, Option<Float>)> {
+    let touches = event.touches();
+    let rect = canvas.get_bounding_client_rect();
+    let points: Vec<Vec2f> = (0..touches.length().min(2))
+        .filter_map(|idx| touches.get(idx))
+        .map(|touch| Vec2f::new(touch.client_x() as Float - rect.left() as Float, touch.client_y() as Float - rect.top() as Float))
+        .collect();
+    match points.as_slice() {
+        [single] => Some((*single, None)),
+        [first, second] => Some((
+            Vec2f::new((first.x + second.x) / 2.0, (first.y + second.y) / 2.0),
+            Some((first.x - second.x).hypot(first.y - second.y)),
+        )),
+        _ => None,
+    }
+}
+
+impl Listeners {
+    pub fn attach(canvas: HtmlCanvasElement, controls: Rc<RefCell<Controls>>) -> Listeners {
+        let mut listeners = Listeners {
+            canvas,
+            closures: Vec::new(),
+        };
+
+        listeners.listen("mousedown", &controls, |_, controls, event| {
+            let event: &MouseEvent = event.unchecked_ref();
+            let mode = if event.button() == 2 || event.shift_key() { DragMode::Pan } else { DragMode::Orbit };
+            controls.pointer_down(mouse_position(event), mode);
+        });
+        listeners.listen("mousemove", &controls, |canvas, controls, event| {
+            controls.pointer_move(mouse_position(event.unchecked_ref()), canvas_size(canvas));
+        });
+        listeners.listen("mouseup", &controls, |canvas, controls, _| {
+            controls.pointer_up(canvas_size(canvas));
+        });
+        listeners.listen("mouseleave", &controls, |_, controls, _| {
+            controls.pointer_cancel();
+        });
+        listeners.listen("contextmenu", &controls, |_, _, event| {
+            event.prevent_default(); // right drag pans
+        });
+        listeners.listen("wheel", &controls, |_, controls, event| {
+            event.prevent_default(); // don't scroll the page
+            let event: &WheelEvent = event.unchecked_ref();
+            controls.wheel(event.delta_y() as Float);
+        });
+
+        listeners.listen("touchstart", &controls, |canvas, controls, event| {
+            event.prevent_default(); // no emulated mouse events
+            let Some((position, spread)) = touch_position(canvas, event.unchecked_ref()) else {
+                return;
+            };
+            let mode = if spread.is_some() { DragMode::Pan } else { DragMode::Orbit };
+            controls.pointer_down(position, mode);
+            controls.pinch(spread);
+        });
+        listeners.listen("touchmove", &controls, |canvas, controls, event| {
+            event.prevent_default();
+            if let Some((position, spread)) = touch_position(canvas, event.unchecked_ref()) {
+                controls.pointer_move(position, canvas_size(canvas));
+                controls.pinch(spread);
+            }
+        });
+        listeners.listen("touchend", &controls, |canvas, controls, event| {
+            event.prevent_default();
+            match touch_position(canvas, event.unchecked_ref()) {
+                // down to one finger: keep going as an orbit, but not as a tap
+                Some((position, _)) => {
+                    controls.pointer_cancel();
+                    controls.pointer_down(position, DragMode::Orbit);
+                    controls.pinch(None);
+                },
+                None => {
+                    controls.pointer_up(canvas_size(canvas));
+                    controls.pinch(None);
+                },
+            }
+        });
+        return listeners;
+    }
+
+    fn listen(
+        &mut self,
+        name: &'static str,
+        controls: &Rc<RefCell<Controls>>,
+        handler: fn(&HtmlCanvasElement, &mut Controls, &Event),
+    ) {
+        let controls = controls.clone();
+        let canvas = self.canvas.clone();
+        let closure = Closure::<dyn FnMut(Event)>::new(move |event: Event| {
+            handler(&canvas, &mut controls.borrow_mut(), &event);
+        });
+        self.canvas
+            .add_event_listener_with_callback(name, closure.as_ref().unchecked_ref())
+            .expect("Could not add event listener");
+        self.closures.push((name, closure));
+    }
+}
+
+impl Drop for Listeners {
+    fn drop(&mut self) {
+        for (name, closure) in &self.closures {
+            // nothing useful to do if it fails, the canvas is probably gone
+            let _ = self.canvas.remove_event_listener_with_callback(name, closure.as_ref().unchecked_ref());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::Vec3;
+
+    fn controls() -> Controls {
+        Controls::new(OrbitCamera::looking_at(Vec3::new(0.0, 0.0, 5.0), Vec3::zero(), 40.0, 0.0, 5.0))
+    }
+
+    #[test]
+    fn test_click_requests_focus() {
+        let mut controls = controls();
+        let size = Vec2f::new(200.0, 100.0);
+        controls.pointer_down(Vec2f::new(50.0, 25.0), DragMode::Orbit);
+        controls.pointer_move(Vec2f::new(51.0, 26.0), size); // within the slop
+        assert!(!controls.is_dragging());
+        controls.pointer_up(size);
+        assert_eq!(controls.focus_request, Some(Vec2f::new(0.25, 0.75)));
+        assert_eq!(controls.view, self::controls().view);
+    }
+
+    #[test]
+    fn test_drag_orbits() {
+        let mut controls = controls();
+        let size = Vec2f::new(200.0, 100.0);
+        controls.pointer_down(Vec2f::new(50.0, 50.0), DragMode::Orbit);
+        controls.pointer_move(Vec2f::new(100.0, 50.0), size);
+        assert!(controls.is_dragging() && controls.changed);
+        // a quarter of the width is a quarter turn
+        assert!((controls.view.yaw + consts::FRAC_PI_2).abs() < 1e-5);
+        controls.pointer_up(size);
+        assert_eq!(controls.focus_request, None);
+        assert!(!controls.is_dragging());
+    }
+
+    #[test]
+    fn test_wheel_and_pinch_zoom() {
+        let mut controls = controls();
+        controls.wheel(-500.0);
+        assert!(controls.view.distance < 5.0);
+        let distance = controls.view.distance;
+        controls.pinch(Some(100.0));
+        controls.pinch(Some(200.0)); // fingers apart: closer
+        assert!((controls.view.distance - distance / 2.0).abs() < 1e-5);
+    }
+}
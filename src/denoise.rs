@@ -0,0 +1,170 @@
+/*
+ * Edge-avoiding à-trous wavelet denoiser.
+ *
+ * See "Edge-Avoiding À-Trous Wavelet Transform for fast Global Illumination
+ * Filtering" (Dammertz et al. 2010). A 5x5 B3-spline kernel is applied with
+ * growing gaps between the taps, and every tap is weighted down when its
+ * color, normal or albedo differs from the center pixel, so the blur stops
+ * at geometry and texture edges.
+ *
+ * The albedo is divided out before filtering and multiplied back in after,
+ * so only the lighting gets blurred.
+ */
+
+use crate::primitives::{Float, Vec3};
+
+const KERNEL: [Float; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DenoiseSettings {
+    pub iterations: u32,    // each one doubles the filter footprint
+    pub sigma_color: Float, // halved every iteration
+    pub sigma_normal: Float,
+    pub sigma_albedo: Float,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        Self {
+            iterations: 4,
+            sigma_color: 0.3,
+            sigma_normal: 0.3,
+            sigma_albedo: 0.2,
+        }
+    }
+}
+
+// Feature buffers collected alongside the color (see `Aov`)
+pub struct Guides<'a> {
+    pub albedo: &'a [Vec3],
+    pub normal: &'a [Vec3],
+}
+
+fn edge_weight(lhs: Vec3, rhs: Vec3, sigma: Float) -> Float {
+    (-(lhs - rhs).length_squared() / (sigma * sigma)).exp()
+}
+
+// Albedo used for demodulation, misses and black surfaces are left alone
+fn demodulation_albedo(albedo: Vec3) -> Vec3 {
+    let eps = 1e-3;
+    Vec3 {
+        x: if albedo.x > eps { albedo.x } else { 1.0 },
+        y: if albedo.y > eps { albedo.y } else { 1.0 },
+        z: if albedo.z > eps { albedo.z } else { 1.0 },
+    }
+}
+
+pub fn denoise(
+    color: &[Vec3],
+    guides: &Guides,
+    width: usize,
+    height: usize,
+    settings: &DenoiseSettings,
+) -> Vec<Vec3> {
+    assert_eq!(color.len(), width * height, "Color buffer doesn't match the image size");
+    assert_eq!(guides.albedo.len(), color.len(), "Albedo buffer doesn't match the color");
+    assert_eq!(guides.normal.len(), color.len(), "Normal buffer doesn't match the color");
+
+    let mut lighting: Vec<Vec3> = color.iter()
+        .zip(guides.albedo.iter())
+        .map(|(c, a)| *c / demodulation_albedo(*a))
+        .collect();
+
+    for iteration in 0..settings.iterations {
+        let step = 1_i32 << iteration;
+        let sigma_color = settings.sigma_color / (1 << iteration) as Float;
+        let filtered: Vec<Vec3> = (0..width * height).map(|idx| {
+            let (x, y) = ((idx % width) as i32, (idx / width) as i32);
+            let center = lighting[idx];
+            let mut sum = Vec3::zero();
+            let mut total_weight = 0.0;
+            for (ky, ky_weight) in KERNEL.iter().enumerate() {
+                let sy = y + (ky as i32 - 2) * step;
+                if sy < 0 || sy >= height as i32 {
+                    continue;
+                }
+                for (kx, kx_weight) in KERNEL.iter().enumerate() {
+                    let sx = x + (kx as i32 - 2) * step;
+                    if sx < 0 || sx >= width as i32 {
+                        continue;
+                    }
+                    let tap = sy as usize * width + sx as usize;
+                    let weight = ky_weight * kx_weight
+                        * edge_weight(center, lighting[tap], sigma_color)
+                        * edge_weight(guides.normal[idx], guides.normal[tap], settings.sigma_normal)
+                        * edge_weight(guides.albedo[idx], guides.albedo[tap], settings.sigma_albedo);
+                    sum += lighting[tap] * weight;
+                    total_weight += weight;
+                }
+            }
+            // the center tap always contributes, so the weight is never zero
+            sum / total_weight
+        }).collect();
+        lighting = filtered;
+    }
+
+    lighting.iter()
+        .zip(guides.albedo.iter())
+        .map(|(l, a)| *l * demodulation_albedo(*a))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random;
+
+    fn variance(pixels: &[Vec3]) -> Float {
+        let mean = pixels.iter().fold(Vec3::zero(), |acc, px| acc + *px) / pixels.len() as Float;
+        pixels.iter().map(|px| (*px - mean).length_squared()).sum::<Float>() / pixels.len() as Float
+    }
+
+    #[test]
+    fn test_flat_noise_is_smoothed() {
+        random::seed(5);
+        let (width, height) = (32, 32);
+        let color: Vec<Vec3> = (0..width * height)
+            .map(|_| Vec3::ones() * (0.45 + 0.1 * random::random() as Float))
+            .collect();
+        let albedo = vec![Vec3::ones() * 0.5; width * height];
+        let normal = vec![Vec3::new(0.0, 1.0, 0.0); width * height];
+        let guides = Guides { albedo: &albedo, normal: &normal };
+        let denoised = denoise(&color, &guides, width, height, &DenoiseSettings::default());
+        assert!(variance(&denoised) < variance(&color) * 0.2);
+    }
+
+    #[test]
+    fn test_normal_edge_is_kept() {
+        // left half faces up and is bright, right half faces sideways and is dark
+        let (width, height) = (16, 8);
+        let left = |idx: usize| idx % width < width / 2;
+        let color: Vec<Vec3> = (0..width * height)
+            .map(|idx| if left(idx) { Vec3::ones() } else { Vec3::ones() * 0.1 })
+            .collect();
+        let normal: Vec<Vec3> = (0..width * height)
+            .map(|idx| if left(idx) { Vec3::new(0.0, 1.0, 0.0) } else { Vec3::new(1.0, 0.0, 0.0) })
+            .collect();
+        let albedo = vec![Vec3::ones(); width * height];
+        let guides = Guides { albedo: &albedo, normal: &normal };
+        let denoised = denoise(&color, &guides, width, height, &DenoiseSettings::default());
+        for (px, expected) in denoised.iter().zip(color.iter()) {
+            assert!((*px - *expected).length() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_albedo_is_restored() {
+        // constant lighting on a checkerboard texture should come back unchanged
+        let (width, height) = (8, 8);
+        let albedo: Vec<Vec3> = (0..width * height)
+            .map(|idx| if (idx + idx / width) % 2 == 0 { Vec3::new(0.8, 0.2, 0.2) } else { Vec3::new(0.2, 0.2, 0.8) })
+            .collect();
+        let color = albedo.iter().map(|a| *a * 0.5).collect::<Vec<_>>();
+        let normal = vec![Vec3::new(0.0, 0.0, 1.0); width * height];
+        let guides = Guides { albedo: &albedo, normal: &normal };
+        let denoised = denoise(&color, &guides, width, height, &DenoiseSettings::default());
+        for (px, expected) in denoised.iter().zip(color.iter()) {
+            assert!((*px - *expected).near_zero());
+        }
+    }
+}
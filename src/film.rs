@@ -0,0 +1,261 @@
+/*
+ * Pixel reconstruction.
+ *
+ * Every camera sample lands somewhere inside its pixel (see `sampler`), and
+ * gets spread over all the pixels whose center is within the filter radius,
+ * weighted by the filter. A pixel's color is the weighted average of what
+ * landed on it. A box of radius 0.5 is the plain per-pixel average, wider
+ * filters trade sharpness for less aliasing.
+ *
+ * Pixel `(x, y)` covers `x..x + 1`, `y..y + 1` in the continuous coordinates
+ * the samples are given in, so its center is at `(x + 0.5, y + 0.5)`.
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::primitives::{Float, Rect, Vec2f, Vec2i, Vec3, consts};
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,       // Mitchell-Netravali with B = C = 1/3, slightly sharpening
+    BlackmanHarris,
+}
+
+impl Filter {
+    pub const ALL: [Filter; 5] = [
+        Filter::Box,
+        Filter::Tent,
+        Filter::Gaussian,
+        Filter::Mitchell,
+        Filter::BlackmanHarris,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Filter::Box => "box",
+            Filter::Tent => "tent",
+            Filter::Gaussian => "gaussian",
+            Filter::Mitchell => "mitchell",
+            Filter::BlackmanHarris => "blackman_harris",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Filter> {
+        Filter::ALL.iter().find(|filter| filter.name() == name).copied()
+    }
+
+    // Radius (in pixels) the filter is usually used with
+    pub fn default_radius(&self) -> Float {
+        match self {
+            Filter::Box => 0.5,
+            Filter::Tent => 1.0,
+            Filter::Gaussian => 1.5,
+            Filter::Mitchell => 2.0,
+            Filter::BlackmanHarris => 1.5,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PixelFilter {
+    pub filter: Filter,
+    pub radius: Float, // in pixels, at least 0.5 so every sample reaches its own pixel
+}
+
+impl PixelFilter {
+    // `radius` of zero or less picks the filter's default one
+    pub fn new(filter: Filter, radius: Float) -> PixelFilter {
+        let radius = if radius <= 0.0 { filter.default_radius() } else { radius.max(0.5) };
+        PixelFilter { filter, radius }
+    }
+
+    // How many pixels away from its own a sample can reach
+    pub fn extent(&self) -> i32 {
+        (self.radius + 0.5).ceil() as i32
+    }
+
+    // 1D profile, the filters are separable
+    fn evaluate_1d(&self, x: Float) -> Float {
+        let r = self.radius;
+        let x = x.abs();
+        if x > r {
+            return 0.0;
+        }
+        match self.filter {
+            Filter::Box => 1.0,
+            Filter::Tent => r - x,
+            Filter::Gaussian => {
+                // shifted down so it reaches zero at the radius
+                let sigma = r / 3.0;
+                let gaussian = |x: Float| (-x * x / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(r)
+            },
+            Filter::Mitchell => {
+                let (b, c) = (1.0 / 3.0, 1.0 / 3.0);
+                let x = 2.0 * x / r;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b)) / 6.0
+                } else {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c)) / 6.0
+                }
+            },
+            Filter::BlackmanHarris => {
+                let t = 2.0 * consts::PI * (0.5 + 0.5 * x / r);
+                0.35875 - 0.48829 * t.cos() + 0.14128 * (2.0 * t).cos() - 0.01168 * (3.0 * t).cos()
+            },
+        }
+    }
+
+    pub fn evaluate(&self, offset: Vec2f) -> Float {
+        self.evaluate_1d(offset.x) * self.evaluate_1d(offset.y)
+    }
+}
+
+impl Default for PixelFilter {
+    fn default() -> Self {
+        PixelFilter::new(Filter::Box, 0.0)
+    }
+}
+
+// Weighted sums of the samples for a region of the image
+pub struct Film {
+    bounds: Rect,
+    sums: Vec<Vec3>,
+    weights: Vec<Float>,
+}
+
+impl Film {
+    pub fn new(bounds: Rect) -> Film {
+        let len = (bounds.w * bounds.h) as usize;
+        Film {
+            bounds,
+            sums: vec![Vec3::zero(); len],
+            weights: vec![0.0; len],
+        }
+    }
+
+    // Film for `bounds`, grown by what the filter can reach and clipped to the image
+    pub fn for_tile(bounds: Rect, img_size: Vec2i, filter: &PixelFilter) -> Film {
+        let extent = filter.extent();
+        let x0 = (bounds.x - extent).max(0);
+        let y0 = (bounds.y - extent).max(0);
+        let x1 = (bounds.x + bounds.w + extent).min(img_size.x);
+        let y1 = (bounds.y + bounds.h + extent).min(img_size.y);
+        Film::new(Rect { x: x0, y: y0, w: x1 - x0, h: y1 - y0 })
+    }
+
+    // Spreads a sample at `position` (continuous pixel coordinates) over the pixels around it
+    pub fn add_sample(&mut self, filter: &PixelFilter, position: Vec2f, color: Vec3) {
+        // pixels whose center is within the radius
+        let x0 = ((position.x - filter.radius - 0.5).floor() as i32 + 1).max(self.bounds.x);
+        let x1 = ((position.x + filter.radius - 0.5).floor() as i32).min(self.bounds.x + self.bounds.w - 1);
+        let y0 = ((position.y - filter.radius - 0.5).floor() as i32 + 1).max(self.bounds.y);
+        let y1 = ((position.y + filter.radius - 0.5).floor() as i32).min(self.bounds.y + self.bounds.h - 1);
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let offset = Vec2f::new(x as Float + 0.5 - position.x, y as Float + 0.5 - position.y);
+                let weight = filter.evaluate(offset);
+                let idx = ((y - self.bounds.y) * self.bounds.w + (x - self.bounds.x)) as usize;
+                self.sums[idx] += color * weight;
+                self.weights[idx] += weight;
+            }
+        }
+    }
+
+    // Adds another film's samples, `other` has to be inside this one
+    pub fn merge(&mut self, other: &Film) {
+        for y in 0..other.bounds.h {
+            let row = (other.bounds.y + y - self.bounds.y) * self.bounds.w + other.bounds.x - self.bounds.x;
+            for x in 0..other.bounds.w {
+                let src = (y * other.bounds.w + x) as usize;
+                let dst = (row + x) as usize;
+                self.sums[dst] += other.sums[src];
+                self.weights[dst] += other.weights[src];
+            }
+        }
+    }
+
+    /*
+     * Final pixel colors, top row first like the other buffers. Negative
+     * lobes (Mitchell) can undershoot, so the result is clamped at zero.
+     */
+    pub fn resolve(&self) -> Vec<Vec3> {
+        (0..self.bounds.h).rev()
+            .flat_map(|y| (0..self.bounds.w).map(move |x| (y * self.bounds.w + x) as usize))
+            .map(|idx| {
+                if self.weights[idx] == 0.0 {
+                    return Vec3::zero();
+                }
+                let color = self.sums[idx] / self.weights[idx];
+                Vec3::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_box_keeps_samples_in_their_pixel() {
+        let filter = PixelFilter::default();
+        let mut film = Film::new(Rect { x: 0, y: 0, w: 3, h: 1 });
+        film.add_sample(&filter, Vec2f::new(1.0, 0.5), Vec3::ones());
+        film.add_sample(&filter, Vec2f::new(1.99, 0.5), Vec3::ones() * 3.0);
+        assert_eq!(film.resolve(), vec![Vec3::zero(), Vec3::ones() * 2.0, Vec3::zero()]);
+    }
+
+    #[test]
+    fn test_filters_are_normalized_by_weight() {
+        // a constant image stays constant, whatever the filter
+        for filter in Filter::ALL {
+            let filter = PixelFilter::new(filter, 0.0);
+            let mut film = Film::new(Rect { x: 0, y: 0, w: 6, h: 6 });
+            for i in 0..36 * 4 {
+                let position = Vec2f::new((i % 12) as Float * 0.5 + 0.25, (i / 12) as Float * 0.5 + 0.25);
+                film.add_sample(&filter, position, Vec3::ones() * 0.5);
+            }
+            for px in film.resolve() {
+                assert!((px - Vec3::ones() * 0.5).near_zero(), "{:?} gave {:?}", filter.filter, px);
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_shapes() {
+        let tent = PixelFilter::new(Filter::Tent, 1.0);
+        assert_eq!(tent.evaluate(Vec2f::new(0.5, 0.0)), 0.5);
+        assert_eq!(tent.evaluate(Vec2f::new(1.5, 0.0)), 0.0);
+        let gaussian = PixelFilter::new(Filter::Gaussian, 1.5);
+        assert!(gaussian.evaluate(Vec2f::new(1.5, 0.0)).abs() < 1e-6);
+        // Mitchell has a negative lobe, Blackman-Harris fades out to almost nothing
+        let mitchell = PixelFilter::new(Filter::Mitchell, 2.0);
+        assert!(mitchell.evaluate(Vec2f::new(1.5, 0.0)) < 0.0);
+        let blackman_harris = PixelFilter::new(Filter::BlackmanHarris, 1.5);
+        assert!(blackman_harris.evaluate(Vec2f::new(1.5, 0.0)) < 1e-4);
+        assert!(blackman_harris.evaluate(Vec2f::zero()) > 0.99);
+    }
+
+    #[test]
+    fn test_merge_offset_tile() {
+        let filter = PixelFilter::new(Filter::Tent, 1.0);
+        let mut image = Film::new(Rect { x: 0, y: 0, w: 4, h: 4 });
+        let mut tile = Film::for_tile(Rect { x: 0, y: 2, w: 4, h: 1 }, Vec2i::new(4, 4), &filter);
+        tile.add_sample(&filter, Vec2f::new(2.5, 2.5), Vec3::ones());
+        image.merge(&tile);
+        // only the center pixel has weight: its neighbors are exactly a radius away
+        let resolved = image.resolve();
+        assert_eq!(resolved[4 + 2], Vec3::ones());
+        assert_eq!(resolved.iter().filter(|px| **px != Vec3::zero()).count(), 1);
+    }
+}
@@ -0,0 +1,425 @@
+/*
+ * Writers for floating point image formats.
+ *
+ * Both take the linear (not tone mapped) radiance of the image, top row
+ * first, so the result can be composited or graded somewhere else.
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::primitives::{Float, Vec3};
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrPixelType {
+    Half,
+    Float,
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExrCompression {
+    None,
+    Zip,
+}
+
+// Largest value RGBE can hold, a mantissa of 255 at the top exponent
+const RGBE_MAX: Float = 255.0 / 256.0 * (1u128 << 127) as Float;
+
+// Radiance RGBE encoding of a single pixel
+fn to_rgbe(color: Vec3) -> [u8; 4] {
+    // clamped, so infinite values (e.g. depth misses) don't overflow the exponent
+    let color = Vec3::new(color.x.min(RGBE_MAX), color.y.min(RGBE_MAX), color.z.min(RGBE_MAX));
+    let v = color.x.max(color.y).max(color.z);
+    if v.is_nan() || v < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // frexp: v = mantissa * 2^exponent, mantissa in [0.5, 1)
+    let mut exponent = v.log2().floor() as i32 + 1;
+    let mut mantissa = v / (exponent as Float).exp2();
+    if mantissa >= 1.0 {
+        mantissa *= 0.5;
+        exponent += 1;
+    } else if mantissa < 0.5 {
+        mantissa *= 2.0;
+        exponent -= 1;
+    }
+    let scale = mantissa * 256.0 / v;
+    let channel = |c: Float| -> u8 { (c.max(0.0) * scale).min(255.0) as u8 };
+    [
+        channel(color.x),
+        channel(color.y),
+        channel(color.z),
+        (exponent + 128).clamp(0, 255) as u8,
+    ]
+}
+
+/*
+ * Adaptive run length encoding for one channel of a scanline.
+ *
+ * Follows the encoder from Greg Ward's original `color.c`: runs of at least
+ * 4 identical bytes become (128 + count, value), the rest is written as
+ * literal chunks of up to 128 bytes.
+ */
+fn rle_channel(data: &[u8], out: &mut Vec<u8>) {
+    const MIN_RUN: usize = 4;
+    let mut cur = 0;
+    while cur < data.len() {
+        let mut beg_run = cur;
+        let mut run_count = 0;
+        let mut old_run_count = 0;
+        // find the next run that is long enough to be worth it
+        while run_count < MIN_RUN && beg_run < data.len() {
+            beg_run += run_count;
+            old_run_count = run_count;
+            run_count = 1;
+            while beg_run + run_count < data.len()
+                && run_count < 127
+                && data[beg_run] == data[beg_run + run_count] {
+                run_count += 1;
+            }
+        }
+        // a short run right before the long one is still cheaper as a run
+        if old_run_count > 1 && old_run_count == beg_run - cur {
+            out.push(128 + old_run_count as u8);
+            out.push(data[cur]);
+            cur = beg_run;
+        }
+        while cur < beg_run {
+            let count = (beg_run - cur).min(128);
+            out.push(count as u8);
+            out.extend_from_slice(&data[cur..cur + count]);
+            cur += count;
+        }
+        if run_count >= MIN_RUN {
+            out.push(128 + run_count as u8);
+            out.push(data[beg_run]);
+            cur += run_count;
+        }
+    }
+}
+
+// Encodes the image as a Radiance `.hdr` file
+pub fn encode_radiance_hdr(pixels: &[Vec3], width: usize, height: usize) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height, "Image buffer doesn't match its dimensions");
+    let mut out = Vec::new();
+    out.extend_from_slice(b"#?RADIANCE\n");
+    out.extend_from_slice(b"FORMAT=32-bit_rle_rgbe\n\n");
+    out.extend_from_slice(format!("-Y {} +X {}\n", height, width).as_bytes());
+
+    for row in pixels.chunks(width) {
+        let rgbe: Vec<[u8; 4]> = row.iter().map(|px| to_rgbe(*px)).collect();
+        if !(8..0x8000).contains(&width) {
+            // RLE is not allowed for these widths, write flat pixels
+            rgbe.iter().for_each(|px| out.extend_from_slice(px));
+            continue;
+        }
+        out.extend_from_slice(&[2, 2, (width >> 8) as u8, (width & 0xFF) as u8]);
+        for channel in 0..4 {
+            let data: Vec<u8> = rgbe.iter().map(|px| px[channel]).collect();
+            rle_channel(&data, &mut out);
+        }
+    }
+    return out;
+}
+
+/*
+ * IEEE 754 half precision from single precision, rounding to nearest even.
+ *
+ * Values too large for a half become infinity, as OpenEXR expects.
+ */
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x007F_FFFF;
+
+    if exponent == 0xFF {
+        // infinity stays infinity, NaN stays NaN (keep it quiet)
+        let nan_bit = if mantissa != 0 { 0x0200 } else { 0 };
+        return sign | 0x7C00 | nan_bit;
+    }
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00; // overflow
+    }
+    if half_exponent <= 0 {
+        // subnormal half (or zero)
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000; // implicit leading one
+        let shift = (14 - half_exponent) as u32;
+        let half_mantissa = mantissa >> shift;
+        let remainder = mantissa & ((1 << shift) - 1);
+        let halfway = 1 << (shift - 1);
+        let round_up = remainder > halfway || (remainder == halfway && (half_mantissa & 1) == 1);
+        return sign | (half_mantissa + round_up as u32) as u16;
+    }
+    let half = ((half_exponent as u32) << 10) | (mantissa >> 13);
+    let remainder = mantissa & 0x1FFF;
+    let round_up = remainder > 0x1000 || (remainder == 0x1000 && (half & 1) == 1);
+    // a carry out of the mantissa correctly bumps the exponent (up to infinity)
+    return sign | (half + round_up as u32) as u16;
+}
+
+fn write_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+/*
+ * ZIP compression as defined by OpenEXR: the bytes are split into even and
+ * odd halves, delta encoded, then deflated with a zlib wrapper.
+ */
+fn exr_zip(raw: &[u8]) -> Vec<u8> {
+    let half = raw.len().div_ceil(2);
+    let mut reordered = vec![0u8; raw.len()];
+    for (idx, byte) in raw.iter().enumerate() {
+        let dest = if idx % 2 == 0 { idx / 2 } else { half + idx / 2 };
+        reordered[dest] = *byte;
+    }
+    let mut prev = reordered.first().copied().unwrap_or(0);
+    for byte in reordered.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(prev).wrapping_add(128);
+        prev = current;
+    }
+    miniz_oxide::deflate::compress_to_vec_zlib(&reordered, 6)
+}
+
+/*
+ * Encodes the image as a single part, scanline OpenEXR file with R, G and B
+ * channels.
+ */
+pub fn encode_openexr(
+    pixels: &[Vec3],
+    width: usize,
+    height: usize,
+    pixel_type: ExrPixelType,
+    compression: ExrCompression,
+) -> Vec<u8> {
+    assert_eq!(pixels.len(), width * height, "Image buffer doesn't match its dimensions");
+    let lines_per_chunk = match compression {
+        ExrCompression::None => 1,
+        ExrCompression::Zip => 16,
+    };
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&20000630_i32.to_le_bytes()); // magic number
+    out.extend_from_slice(&2_i32.to_le_bytes()); // version 2, single part scanline
+
+    // channel list, sorted by name like the spec wants
+    let mut channels = Vec::new();
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        let type_id: i32 = match pixel_type {
+            ExrPixelType::Half => 1,
+            ExrPixelType::Float => 2,
+        };
+        channels.extend_from_slice(&type_id.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]); // pLinear + reserved
+        channels.extend_from_slice(&1_i32.to_le_bytes()); // x sampling
+        channels.extend_from_slice(&1_i32.to_le_bytes()); // y sampling
+    }
+    channels.push(0);
+    write_attribute(&mut out, "channels", "chlist", &channels);
+
+    let compression_id: u8 = match compression {
+        ExrCompression::None => 0,
+        ExrCompression::Zip => 3,
+    };
+    write_attribute(&mut out, "compression", "compression", &[compression_id]);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+    write_attribute(&mut out, "dataWindow", "box2i", &window);
+    write_attribute(&mut out, "displayWindow", "box2i", &window);
+    write_attribute(&mut out, "lineOrder", "lineOrder", &[0]); // increasing y
+    write_attribute(&mut out, "pixelAspectRatio", "float", &1.0_f32.to_le_bytes());
+    let center: Vec<u8> = [0.0_f32, 0.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+    write_attribute(&mut out, "screenWindowCenter", "v2f", &center);
+    write_attribute(&mut out, "screenWindowWidth", "float", &1.0_f32.to_le_bytes());
+    out.push(0); // end of header
+
+    // every scanline holds all of B, then all of G, then all of R
+    let encode_line = |row: &[Vec3], raw: &mut Vec<u8>| {
+        // the file has floats whatever the build uses
+        let channel_values: [fn(&Vec3) -> f32; 3] = [|px| px.z as f32, |px| px.y as f32, |px| px.x as f32];
+        for value in channel_values {
+            for px in row {
+                match pixel_type {
+                    ExrPixelType::Half => raw.extend_from_slice(&f32_to_f16(value(px)).to_le_bytes()),
+                    ExrPixelType::Float => raw.extend_from_slice(&value(px).to_le_bytes()),
+                }
+            }
+        }
+    };
+
+    let chunks: Vec<(i32, Vec<u8>)> = pixels
+        .chunks(width * lines_per_chunk)
+        .enumerate()
+        .map(|(idx, block)| {
+            let mut raw = Vec::new();
+            block.chunks(width).for_each(|row| encode_line(row, &mut raw));
+            let data = match compression {
+                ExrCompression::None => raw,
+                ExrCompression::Zip => {
+                    // incompressible chunks are stored as-is
+                    let zipped = exr_zip(&raw);
+                    if zipped.len() < raw.len() { zipped } else { raw }
+                }
+            };
+            ((idx * lines_per_chunk) as i32, data)
+        })
+        .collect();
+
+    // offset table, then the chunks themselves
+    let mut offset = (out.len() + chunks.len() * 8) as u64;
+    for (_, data) in chunks.iter() {
+        out.extend_from_slice(&offset.to_le_bytes());
+        offset += (8 + data.len()) as u64;
+    }
+    for (y, data) in chunks {
+        out.extend_from_slice(&y.to_le_bytes());
+        out.extend_from_slice(&(data.len() as i32).to_le_bytes());
+        out.extend_from_slice(&data);
+    }
+    return out;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_half_conversion() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3C00);
+        assert_eq!(f32_to_f16(-2.0), 0xC000);
+        assert_eq!(f32_to_f16(0.5), 0x3800);
+        assert_eq!(f32_to_f16(65504.0), 0x7BFF);
+        assert_eq!(f32_to_f16(1e6), 0x7C00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7C00);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7C00, 0x7C00);
+        // smallest subnormal half
+        assert_eq!(f32_to_f16(5.960_464_5e-8), 0x0001);
+        // 1 + 2^-11 is exactly halfway between two halves, rounds to even
+        assert_eq!(f32_to_f16(1.000_488_3), 0x3C00);
+    }
+
+    #[test]
+    fn test_rgbe() {
+        assert_eq!(to_rgbe(Vec3::zero()), [0, 0, 0, 0]);
+        assert_eq!(to_rgbe(Vec3::ones()), [128, 128, 128, 129]);
+        assert_eq!(to_rgbe(Vec3::new(0.5, 0.25, 0.0)), [128, 64, 0, 128]);
+        assert_eq!(to_rgbe(Vec3::ones() * Float::INFINITY), [255, 255, 255, 255]);
+        assert_eq!(to_rgbe(Vec3::new(Float::INFINITY, 1.0, -Float::INFINITY)), [255, 0, 0, 255]);
+    }
+
+    fn rle_decode(data: &[u8], len: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut idx = 0;
+        while out.len() < len {
+            let count = data[idx] as usize;
+            if count > 128 {
+                out.extend(std::iter::repeat_n(data[idx + 1], count - 128));
+                idx += 2;
+            } else {
+                out.extend_from_slice(&data[idx + 1..idx + 1 + count]);
+                idx += 1 + count;
+            }
+        }
+        assert_eq!(idx, data.len());
+        out
+    }
+
+    #[test]
+    fn test_rle_round_trip() {
+        let data: Vec<u8> = [1, 2, 3, 3, 3, 3, 3, 3, 4, 5, 5, 6]
+            .iter()
+            .copied()
+            .chain(std::iter::repeat_n(9, 300))
+            .chain((0..200).map(|v| v as u8))
+            .collect();
+        let mut encoded = Vec::new();
+        rle_channel(&data, &mut encoded);
+        assert!(encoded.len() < data.len());
+        assert_eq!(rle_decode(&encoded, data.len()), data);
+    }
+
+    #[test]
+    fn test_hdr_header() {
+        let pixels = vec![Vec3::new(0.1, 0.2, 0.3); 16 * 4];
+        let file = encode_radiance_hdr(&pixels, 16, 4);
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 4 +X 16\n";
+        assert!(file.starts_with(header));
+        // new-style RLE marker at the start of the first scanline
+        assert_eq!(file[header.len()..header.len() + 4], [2, 2, 0, 16]);
+    }
+
+    #[test]
+    fn test_exr_layout() {
+        let (width, height) = (5, 3);
+        let pixels: Vec<Vec3> = (0..width * height)
+            .map(|v| Vec3::new(v as Float, 0.5, -1.0))
+            .collect();
+        let file = encode_openexr(&pixels, width, height, ExrPixelType::Float, ExrCompression::None);
+        assert_eq!(file[0..4], [0x76, 0x2f, 0x31, 0x01]);
+
+        // the last chunk is the last scanline: y, size, then B, G, R
+        let line_bytes = width * 3 * 4;
+        let last = &file[file.len() - line_bytes - 8..];
+        assert_eq!(i32::from_le_bytes(last[0..4].try_into().unwrap()), 2);
+        assert_eq!(i32::from_le_bytes(last[4..8].try_into().unwrap()), line_bytes as i32);
+        let red_start = 8 + width * 2 * 4;
+        let red = f32::from_le_bytes(last[red_start..red_start + 4].try_into().unwrap());
+        assert_eq!(red, 10.0);
+    }
+
+    // screenWindowWidth is the last attribute written, the header ends right after it
+    fn exr_header_len(file: &[u8]) -> usize {
+        let marker = b"screenWindowWidth\0float\0";
+        let pos = file.windows(marker.len())
+            .position(|window| window == marker)
+            .expect("Missing screenWindowWidth attribute");
+        pos + marker.len() + 4 + 4 + 1
+    }
+
+    #[test]
+    fn test_exr_zip_round_trip() {
+        let (width, height) = (32, 20);
+        let pixels = vec![Vec3::new(0.25, 0.5, 1.0); width * height];
+        let file = encode_openexr(&pixels, width, height, ExrPixelType::Half, ExrCompression::Zip);
+        let uncompressed = encode_openexr(&pixels, width, height, ExrPixelType::Half, ExrCompression::None);
+        assert!(file.len() < uncompressed.len());
+
+        // two chunks (16 + 4 lines), the first one starts right after the offset table
+        let table = exr_header_len(&file);
+        let first_offset = u64::from_le_bytes(file[table..table + 8].try_into().unwrap());
+        assert_eq!(first_offset as usize, table + 16);
+
+        let chunk = &file[table + 16..];
+        assert_eq!(i32::from_le_bytes(chunk[0..4].try_into().unwrap()), 0);
+        let size = i32::from_le_bytes(chunk[4..8].try_into().unwrap()) as usize;
+        let inflated = miniz_oxide::inflate::decompress_to_vec_zlib(&chunk[8..8 + size]).unwrap();
+        assert_eq!(inflated.len(), width * 16 * 3 * 2);
+
+        // undo the predictor and the reordering, the first value is blue as a half
+        let mut predicted = inflated.clone();
+        for idx in 1..predicted.len() {
+            predicted[idx] = predicted[idx - 1].wrapping_add(inflated[idx]).wrapping_sub(128);
+        }
+        let half = predicted.len() / 2;
+        let first = u16::from_le_bytes([predicted[0], predicted[half]]);
+        assert_eq!(first, f32_to_f16(1.0));
+    }
+}
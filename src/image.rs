@@ -0,0 +1,233 @@
+use crate::primitives::{Float, Vec3};
+
+/*
+ * A plain RGB image, top row first.
+ *
+ * What the values mean (linear radiance, display referred 0..1, ...) depends
+ * on where the image came from.
+ */
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Vec3>,
+}
+
+// Splits the PPM header into whitespace separated tokens, skipping comments
+struct PpmTokens<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PpmTokens<'a> {
+    fn next_token(&mut self) -> Option<&'a [u8]> {
+        loop {
+            match self.bytes.get(self.pos)? {
+                b'#' => {
+                    while self.bytes.get(self.pos).is_some_and(|b| *b != b'\n') {
+                        self.pos += 1;
+                    }
+                },
+                b if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+        let start = self.pos;
+        while self.bytes.get(self.pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+        Some(&self.bytes[start..self.pos])
+    }
+
+    fn next_number(&mut self, what: &str) -> Result<u32, String> {
+        let token = self.next_token().ok_or(format!("PPM ended before the {}", what))?;
+        std::str::from_utf8(token)
+            .ok()
+            .and_then(|text| text.parse().ok())
+            .ok_or(format!("Invalid {} in PPM: {}", what, String::from_utf8_lossy(token)))
+    }
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Image {
+        Image {
+            width,
+            height,
+            pixels: vec![Vec3::zero(); width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Vec3 {
+        self.pixels[y * self.width + x]
+    }
+
+    /*
+     * Reads an ASCII (P3) or binary (P6) PPM file.
+     *
+     * Values are scaled to 0..1 by the file's maximum value, but otherwise
+     * left as they are (i.e. still gamma encoded).
+     */
+    pub fn from_ppm(bytes: &[u8]) -> Result<Image, String> {
+        let mut tokens = PpmTokens { bytes, pos: 0 };
+        let binary = match tokens.next_token() {
+            Some(b"P3") => false,
+            Some(b"P6") => true,
+            _ => return Err("Not a P3 or P6 PPM file".to_string()),
+        };
+        let width = tokens.next_number("width")? as usize;
+        let height = tokens.next_number("height")? as usize;
+        let max_value = tokens.next_number("maximum value")?;
+        if max_value == 0 || max_value > 65535 {
+            return Err(format!("PPM maximum value out of range: {}", max_value));
+        }
+        let scale = 1.0 / max_value as Float;
+        // a corrupt header shouldn't overflow its way to a panic
+        let count = width.checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or(format!("PPM size out of range: {}x{}", width, height))?;
+
+        let samples: Vec<u32> = if binary {
+            // exactly one whitespace byte separates the header from the data
+            let start = tokens.pos + 1;
+            let sample_size = if max_value < 256 { 1 } else { 2 };
+            let end = count.checked_mul(sample_size)
+                .and_then(|len| len.checked_add(start))
+                .ok_or("PPM pixel data is truncated".to_string())?;
+            let data = bytes.get(start..end)
+                .ok_or("PPM pixel data is truncated".to_string())?;
+            if sample_size == 1 {
+                data.iter().map(|b| *b as u32).collect()
+            } else {
+                data.chunks(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]]) as u32).collect()
+            }
+        } else {
+            (0..count)
+                .map(|_| tokens.next_number("pixel value"))
+                .collect::<Result<_, _>>()?
+        };
+
+        let pixels = samples
+            .chunks(3)
+            .map(|rgb| Vec3::new(rgb[0] as Float, rgb[1] as Float, rgb[2] as Float) * scale)
+            .collect();
+        Ok(Image { width, height, pixels })
+    }
+
+    // Writes an ASCII PPM, values are clamped to 0..1 and scaled to 0..255
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut text = format!("P3\n{} {}\n255\n", self.width, self.height);
+        let to_byte = |c: Float| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for pixel in self.pixels.iter() {
+            text.push_str(&format!("{} {} {}\n", to_byte(pixel.x), to_byte(pixel.y), to_byte(pixel.z)));
+        }
+        return text.into_bytes();
+    }
+
+    // Writes an 8-bit RGB PNG, values are clamped and scaled like `to_ppm()`
+    pub fn to_png(&self) -> Vec<u8> {
+        let to_byte = |c: Float| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        // every row starts with its filter type, 0 is none
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+            raw.extend(row.iter().flat_map(|px| [to_byte(px.x), to_byte(px.y), to_byte(px.z)]));
+        }
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bits, RGB, deflate, no filter, no interlace
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        write_png_chunk(&mut png, b"IHDR", &header);
+        write_png_chunk(&mut png, b"IDAT", &miniz_oxide::deflate::compress_to_vec_zlib(&raw, 6));
+        write_png_chunk(&mut png, b"IEND", &[]);
+        return png;
+    }
+}
+
+fn write_png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(kind.iter().chain(data.iter()).copied());
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// CRC-32 as PNG (and zip) use it, bit by bit: the chunks are few and small
+fn crc32(bytes: impl Iterator<Item = u8>) -> u32 {
+    let crc = bytes.fold(0xFFFF_FFFF_u32, |crc, byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 }
+        })
+    });
+    return !crc;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_small_ppm() {
+        let image = Image::from_ppm(include_bytes!("small.ppm")).unwrap();
+        assert_eq!(image.width, 80);
+        assert_eq!(image.height, 53);
+        assert_eq!(image.pixels.len(), 80 * 53);
+        assert!((image.get(0, 0) - Vec3::new(220.0, 235.0, 255.0) / 255.0).near_zero());
+    }
+
+    #[test]
+    fn test_read_p6() {
+        let mut file = b"P6\n# a comment\n2 1\n255\n".to_vec();
+        file.extend_from_slice(&[255, 0, 51, 0, 255, 0]);
+        let image = Image::from_ppm(&file).unwrap();
+        assert!((image.get(0, 0) - Vec3::new(1.0, 0.0, 0.2)).near_zero());
+        assert!((image.get(1, 0) - Vec3::new(0.0, 1.0, 0.0)).near_zero());
+    }
+
+    #[test]
+    fn test_read_p6_16bit() {
+        let mut file = b"P6 1 1 65535\n".to_vec();
+        file.extend_from_slice(&[0xFF, 0xFF, 0x00, 0x00, 0x80, 0x00]);
+        let image = Image::from_ppm(&file).unwrap();
+        assert_eq!(image.get(0, 0).x, 1.0);
+        assert_eq!(image.get(0, 0).y, 0.0);
+    }
+
+    #[test]
+    fn test_ppm_round_trip() {
+        let mut image = Image::new(3, 2);
+        image.pixels[4] = Vec3::new(1.0, 0.2, 0.6);
+        let parsed = Image::from_ppm(&image.to_ppm()).unwrap();
+        assert_eq!((parsed.width, parsed.height), (3, 2));
+        for (lhs, rhs) in parsed.pixels.iter().zip(image.pixels.iter()) {
+            assert!((*lhs - *rhs).length() < 1.0 / 255.0);
+        }
+    }
+
+    #[test]
+    fn test_png_layout() {
+        assert_eq!(crc32(b"IEND".iter().copied()), 0xAE42_6082);
+        let mut image = Image::new(2, 1);
+        image.pixels[1] = Vec3::new(1.0, 0.5, 0.0);
+        let png = image.to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 2, 0, 0, 0, 1]);
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let raw = miniz_oxide::inflate::decompress_to_vec_zlib(&png[41..41 + idat_len]).unwrap();
+        assert_eq!(raw, vec![0, 0, 0, 0, 255, 128, 0]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+    }
+
+    #[test]
+    fn test_bad_ppm() {
+        assert!(Image::from_ppm(b"P5 1 1 255 0").is_err());
+        assert!(Image::from_ppm(b"P3 2 2 255 0 0 0").is_err());
+        assert!(Image::from_ppm(b"P6 2 2 255\n\x00").is_err());
+        assert!(Image::from_ppm(b"P6 4294967295 4294967295 65535\n\x00").is_err());
+        assert!(Image::from_ppm(b"P3 4294967295 4294967295 255 0").is_err());
+    }
+}
@@ -0,0 +1,364 @@
+/*
+ * Integrators: what a camera ray brings back.
+ *
+ * The path tracer is the real thing, the others are cheap previews for
+ * setting up a scene. `RenderProperties::integrator` picks one at runtime.
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::compare::heat_color;
+use crate::primitives::{Float, Ray, Vec3};
+use crate::renderer::RenderProperties;
+use crate::sampler;
+use crate::scene::{self, HitRecord, Scene};
+use crate::spectrum;
+
+const SKY_COLOR: Vec3 = Vec3 { x: 0.5, y: 0.7, z: 1.0};
+
+// Anything in front of a ray counts as a hit: rays leaving a surface start
+// off it (see `HitRecord::spawn_ray`), so they can't hit it again
+const T_MIN: Float = 0.0;
+
+/*
+ * What the integrators are handed along with the camera ray: where it hits
+ * first. The renderer traces that once per sample and shares it with the
+ * AOVs and every wavelength, instead of each of them tracing it again.
+ */
+pub fn first_hit(ray: Ray, scene: &Scene) -> Option<HitRecord> {
+    scene.world.hit(ray, T_MIN, Float::INFINITY)
+}
+
+pub trait Integrator {
+    // RGB radiance arriving along a camera ray, `hit` being its `first_hit()`
+    fn radiance(&self, ray: Ray, hit: Option<HitRecord>, scene: &Scene, properties: &RenderProperties) -> Vec3;
+
+    // Radiance at a single wavelength. Integrators without a spectral version upsample their RGB.
+    fn spectral_radiance(
+        &self,
+        ray: Ray,
+        hit: Option<HitRecord>,
+        scene: &Scene,
+        properties: &RenderProperties,
+        lambda: Float,
+    ) -> Float {
+        spectrum::rgb_to_spectrum(self.radiance(ray, hit, scene, properties), lambda)
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IntegratorKind {
+    PathTracer,
+    AmbientOcclusion,
+    Normals,
+    HitDistance,       // heatmap of the distance to the first hit
+    IntersectionCount, // heatmap of the ray/object tests the first hit took
+}
+
+impl IntegratorKind {
+    pub const ALL: [IntegratorKind; 5] = [
+        IntegratorKind::PathTracer,
+        IntegratorKind::AmbientOcclusion,
+        IntegratorKind::Normals,
+        IntegratorKind::HitDistance,
+        IntegratorKind::IntersectionCount,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            IntegratorKind::PathTracer => "path",
+            IntegratorKind::AmbientOcclusion => "ao",
+            IntegratorKind::Normals => "normals",
+            IntegratorKind::HitDistance => "distance",
+            IntegratorKind::IntersectionCount => "tests",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IntegratorKind> {
+        IntegratorKind::ALL.iter().find(|kind| kind.name() == name).copied()
+    }
+}
+
+// Which integrator to use, and the knobs of the preview ones
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct IntegratorSettings {
+    pub kind: IntegratorKind,
+    pub ao_radius: Float,
+    pub max_distance: Float, // white in the hit distance heatmap
+    pub max_tests: u32,    // white in the intersection count heatmap
+}
+
+impl IntegratorSettings {
+    pub fn build(&self) -> Box<dyn Integrator> {
+        match self.kind {
+            IntegratorKind::PathTracer => Box::new(PathTracer),
+            IntegratorKind::AmbientOcclusion => Box::new(AmbientOcclusion { radius: self.ao_radius }),
+            IntegratorKind::Normals => Box::new(Normals),
+            IntegratorKind::HitDistance => Box::new(HitDistance { max_distance: self.max_distance }),
+            IntegratorKind::IntersectionCount => Box::new(IntersectionCount { max_tests: self.max_tests }),
+        }
+    }
+}
+
+impl Default for IntegratorSettings {
+    fn default() -> Self {
+        Self {
+            kind: IntegratorKind::PathTracer,
+            ao_radius: 1.0,
+            max_distance: 20.0,
+            max_tests: 1000,
+        }
+    }
+}
+
+fn sky_color(ray: Ray) -> Vec3 {
+    let unitdir = Vec3::as_unit(ray.dir);
+    let t = 0.5 * (unitdir.y + 1.0);
+    return Vec3::ones() * (1.0 - t) + SKY_COLOR * t;
+}
+
+/*
+ * Russian roulette: past `roulette_depth` bounces, paths carrying little
+ * energy are ended at random. Surviving ones have to be divided by the
+ * returned survival probability, which keeps the estimate unbiased. `None`
+ * means the path ends here.
+ */
+fn roulette(throughput: Float, bounce: u32, properties: &RenderProperties) -> Option<Float> {
+    match properties.roulette_depth {
+        Some(min_depth) if bounce >= min_depth => {
+            // capped so even bright paths end eventually
+            let survival = throughput.clamp(0.0, 0.95);
+            if sampler::next_1d() < survival { Some(survival) } else { None }
+        },
+        _ => Some(1.0),
+    }
+}
+
+/*
+ * Unidirectional path tracer, lit by the sky only.
+ *
+ * A loop rather than recursion, so deep paths don't eat the (small) wasm
+ * stack. Paths that get absorbed or run out of bounces bring back nothing.
+ */
+pub struct PathTracer;
+
+impl Integrator for PathTracer {
+    fn radiance(&self, ray: Ray, hit: Option<HitRecord>, scene: &Scene, properties: &RenderProperties) -> Vec3 {
+        let mut radiance = Vec3::zero();
+        let mut throughput = Vec3::ones();
+        let mut ray = ray;
+        for bounce in 0..properties.bounces {
+            let hit = if bounce == 0 { hit } else { scene.world.hit(ray, T_MIN, Float::INFINITY) };
+            let Some(record) = hit else {
+                radiance += throughput * sky_color(ray);
+                break;
+            };
+            let mut scattered = Ray {
+                orig: Vec3::zero(),
+                dir: Vec3::zero(),
+            };
+            let mut attenuation = Vec3::zero();
+            if !scene.material(record.material).scatter(ray, &record, &mut attenuation, &mut scattered) {
+                break;
+            }
+            throughput *= attenuation;
+            let Some(survival) = roulette(throughput.x.max(throughput.y).max(throughput.z), bounce, properties) else {
+                break;
+            };
+            throughput /= survival;
+            ray = scattered;
+        }
+        return radiance;
+    }
+
+    // Same as `radiance()`, but carrying a single wavelength instead of RGB
+    fn spectral_radiance(
+        &self,
+        ray: Ray,
+        hit: Option<HitRecord>,
+        scene: &Scene,
+        properties: &RenderProperties,
+        lambda: Float,
+    ) -> Float {
+        let mut radiance = 0.0;
+        let mut throughput = 1.0;
+        let mut ray = ray;
+        for bounce in 0..properties.bounces {
+            let hit = if bounce == 0 { hit } else { scene.world.hit(ray, T_MIN, Float::INFINITY) };
+            let Some(record) = hit else {
+                // the sky is treated like any other RGB color and upsampled
+                radiance += throughput * spectrum::rgb_to_spectrum(sky_color(ray), lambda);
+                break;
+            };
+            let mut scattered = Ray {
+                orig: Vec3::zero(),
+                dir: Vec3::zero(),
+            };
+            let mut attenuation = 0.0;
+            if !scene.material(record.material).scatter_spectral(ray, &record, lambda, &mut attenuation, &mut scattered) {
+                break;
+            }
+            throughput *= attenuation;
+            let Some(survival) = roulette(throughput, bounce, properties) else {
+                break;
+            };
+            throughput /= survival;
+            ray = scattered;
+        }
+        return radiance;
+    }
+}
+
+/*
+ * Fraction of the hemisphere above the first hit that is open within
+ * `radius`, one cosine weighted probe per sample. Misses are white.
+ */
+pub struct AmbientOcclusion {
+    pub radius: Float,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, _ray: Ray, hit: Option<HitRecord>, scene: &Scene, _properties: &RenderProperties) -> Vec3 {
+        let Some(record) = hit else {
+            return Vec3::ones();
+        };
+        let dir = record.normal + sampler::to_unit_vector(sampler::next_2d());
+        if dir.near_zero() {
+            return Vec3::ones();
+        }
+        let probe = record.spawn_ray(Vec3::as_unit(dir));
+        match scene.world.hit(probe, T_MIN, self.radius) {
+            Some(_) => Vec3::zero(),
+            None => Vec3::ones(),
+        }
+    }
+}
+
+// Normal at the first hit, mapped to 0..1, black for misses
+pub struct Normals;
+
+impl Integrator for Normals {
+    fn radiance(&self, _ray: Ray, hit: Option<HitRecord>, _scene: &Scene, _properties: &RenderProperties) -> Vec3 {
+        match hit {
+            Some(record) => (record.normal + Vec3::ones()) * 0.5,
+            None => Vec3::zero(),
+        }
+    }
+}
+
+// Distance along the ray to the first hit, black to white through red and yellow. Misses are black.
+pub struct HitDistance {
+    pub max_distance: Float,
+}
+
+impl Integrator for HitDistance {
+    fn radiance(&self, ray: Ray, hit: Option<HitRecord>, _scene: &Scene, _properties: &RenderProperties) -> Vec3 {
+        match hit {
+            Some(record) => heat_color(record.t * ray.dir.length() / self.max_distance),
+            None => Vec3::zero(),
+        }
+    }
+}
+
+/*
+ * How many ray/object intersection tests finding the first hit took. Shows
+ * where the acceleration structure (or lack thereof) does the most work.
+ * Traces the camera ray again to count them, it's only a preview.
+ */
+pub struct IntersectionCount {
+    pub max_tests: u32,
+}
+
+impl Integrator for IntersectionCount {
+    fn radiance(&self, ray: Ray, _hit: Option<HitRecord>, scene: &Scene, _properties: &RenderProperties) -> Vec3 {
+        let before = scene::intersection_tests();
+        scene.world.hit(ray, T_MIN, Float::INFINITY);
+        let tests = scene::intersection_tests() - before;
+        heat_color(tests as Float / self.max_tests as Float)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sampler::Sampler;
+    use crate::scene::{Camera, Hittable, Material};
+
+    fn properties() -> RenderProperties {
+        RenderProperties {
+            samples: 1,
+            sampler: Sampler::Random,
+            roulette_depth: None,
+            ..Default::default()
+        }
+    }
+
+    fn trace(integrator: &dyn Integrator, ray: Ray, scene: &Scene, properties: &RenderProperties) -> Vec3 {
+        integrator.radiance(ray, first_hit(ray, scene), scene, properties)
+    }
+
+    // a white diffuse ground plane (well, a huge sphere) and a small sphere resting on it
+    fn scene() -> Scene {
+        let mut world = Hittable::HittableList { hittables: Vec::new() };
+        let white = Material::Lambertian { albedo: Vec3::ones() };
+        world.push(Hittable::Sphere { center: Vec3::new(0.0, -1000.0, 0.0), radius: 1000.0, material: white });
+        world.push(Hittable::Sphere { center: Vec3::new(0.0, 0.5, 0.0), radius: 0.5, material: white });
+        Scene::new(
+            Camera::new(Vec3::new(0.0, 0.5, 5.0), Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 1.0, 0.0), 30.0, 1.0, 0.0, 5.0),
+            world,
+        )
+    }
+
+    #[test]
+    fn test_path_tracer_sees_the_sky() {
+        let up = Ray { orig: Vec3::new(0.0, 2.0, 0.0), dir: Vec3::new(0.0, 1.0, 0.0) };
+        assert_eq!(trace(&PathTracer, up, &scene(), &properties()), SKY_COLOR);
+        // out of bounces before reaching the sky
+        let down = Ray { orig: Vec3::new(0.0, 2.0, 0.0), dir: Vec3::new(0.0, -1.0, 0.0) };
+        let no_bounces = RenderProperties { bounces: 0, ..properties() };
+        assert_eq!(trace(&PathTracer, down, &scene(), &no_bounces), Vec3::zero());
+    }
+
+    #[test]
+    fn test_white_furnace_ish() {
+        // every surface is white, so whatever comes back is sky light: never darker than
+        // the horizon, never brighter than white
+        let ray = Ray { orig: Vec3::new(0.0, 0.5, 5.0), dir: Vec3::new(0.0, 0.0, -1.0) };
+        for _ in 0..64 {
+            let radiance = trace(&PathTracer, ray, &scene(), &properties());
+            assert!(radiance.x <= 1.0 && radiance.x >= SKY_COLOR.x - 1e-4, "{:?}", radiance);
+        }
+    }
+
+    #[test]
+    fn test_preview_integrators() {
+        let scene = scene();
+        let at_sphere = Ray { orig: Vec3::new(0.0, 0.5, 5.0), dir: Vec3::new(0.0, 0.0, -1.0) };
+        let normal = trace(&Normals, at_sphere, &scene, &properties());
+        assert!((normal - Vec3::new(0.5, 0.5, 1.0)).near_zero());
+
+        let at_sky = Ray { orig: Vec3::new(0.0, 0.5, 5.0), dir: Vec3::new(0.0, 1.0, 0.0) };
+        let ao = AmbientOcclusion { radius: 1.0 };
+        assert_eq!(trace(&ao, at_sky, &scene, &properties()), Vec3::ones());
+        // where the sphere touches the ground about half of the probes are blocked
+        let at_contact = Ray { orig: Vec3::new(0.0, 0.5, 5.0), dir: Vec3::new(0.0, -0.5, -4.55) };
+        let open = (0..256).map(|_| trace(&ao, at_contact, &scene, &properties()).x).sum::<Float>() / 256.0;
+        assert!(open < 0.9, "{}", open);
+
+        // the sphere is 4.5 away, the ground plane a bit further
+        let distance = HitDistance { max_distance: 9.0 };
+        assert_eq!(trace(&distance, at_sphere, &scene, &properties()), heat_color(0.5));
+        assert_eq!(trace(&distance, at_sky, &scene, &properties()), Vec3::zero());
+        // no acceleration structure: every ray tests both spheres
+        let tests = IntersectionCount { max_tests: 4 };
+        assert_eq!(trace(&tests, at_sky, &scene, &properties()), heat_color(0.5));
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        for kind in IntegratorKind::ALL {
+            assert_eq!(IntegratorKind::from_name(kind.name()), Some(kind));
+        }
+    }
+}
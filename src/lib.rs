@@ -1,16 +1,60 @@
+pub mod adaptive;
+pub mod animation;
+pub mod aov;
+pub mod async_render;
+pub mod blue_noise;
+pub mod compare;
+pub mod controls;
+pub mod denoise;
+pub mod film;
+pub mod hdr;
+pub mod image;
+pub mod integrator;
+pub mod materials;
+pub mod orbit;
+pub mod output;
+pub mod pick;
+pub mod primitives;
+pub mod random;
+pub mod sampler;
+pub mod scene;
+pub mod scene_file;
+pub mod spheres;
+#[cfg(feature = "simd")]
+pub mod simd;
+pub mod renderer;
+pub mod spectrum;
+pub mod stats;
 
-mod primitives;
-mod scene;
-mod renderer;
+#[cfg(all(feature = "simd", feature = "f64"))]
+compile_error!("The simd feature only works with f32, turn off f64");
 
 extern crate console_error_panic_hook;
+use std::cell::{Cell, RefCell};
 use std::panic;
+use std::rc::Rc;
 
-use primitives::{Vec2i, Vec2f, Vec3};
-use renderer::{RenderProperties, Tile};
-use scene::{Camera, Scene};
+use adaptive::{AdaptiveRender, AdaptiveSampling};
+use aov::Aov;
+use async_render::{AsyncRender, FRAME_BUDGET_MS};
+use controls::{Controls, Listeners};
+use denoise::{DenoiseSettings, Guides};
+use film::{Filter, PixelFilter};
+use integrator::IntegratorKind;
+use output::{OutputSettings, ToneMapper};
+use pick::Pick;
+use primitives::{Float, Vec2i, Vec2f, Vec3};
+use hdr::{ExrCompression, ExrPixelType};
+use renderer::{AovBuffers, RenderProperties};
+use sampler::Sampler;
+use scene::{CameraModel, MaterialParams, Scene};
+use spectrum::ColorMode;
+use stats::RenderStats;
 use wasm_bindgen::{prelude::*, Clamped};
-use web_sys::ImageData;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+// the preview shown while dragging the camera is this many times smaller
+const PREVIEW_SCALE: i32 = 4;
 
 /*
  * Renderer struct to represent the rendering machinery.
@@ -20,12 +64,23 @@ use web_sys::ImageData;
  */
 #[wasm_bindgen]
 pub struct Renderer {
-    aspect_ratio: f32,
+    aspect_ratio: Float,
     bounds: Vec2i,
     render_config: RenderProperties,
+    output: OutputSettings,
     scene: Scene,
-    running: bool,
-    signal_to_stop: bool, 
+    // shared with the async render, which fills them in when it's done
+    buffers: Rc<RefCell<AovBuffers>>, // every pass of the last render, top row first
+    passes: Rc<Cell<u32>>, // one sample per pixel progressive passes in `buffers`
+    adaptive_render: Option<AdaptiveRender>, // the progressive one, when sampling adaptively
+    stats: Rc<RefCell<RenderStats>>, // what went into `buffers`
+    stats_overlay: bool,
+    controls: Rc<RefCell<Controls>>,
+    listeners: Option<Listeners>,
+    display_pass: Aov,
+    denoise: Option<DenoiseSettings>,
+    running: Rc<Cell<bool>>, // an async render is going
+    signal_to_stop: Rc<Cell<bool>>, // for the async render going, if any
 }
 
 #[wasm_bindgen]
@@ -40,64 +95,383 @@ impl Renderer {
         };
 
         let render_config = RenderProperties {
-            samples: 10,
-            bounces: 50
+            // collect everything so JS can switch passes without rendering again
+            aovs: Aov::ALL.iter().copied().filter(|aov| *aov != Aov::Color).collect(),
+            ..Default::default()
         };
 
         // Scene (now includes camera)
-        let scene = Scene {
-            camera: Camera::new(
-                Vec3::new(13.0, 2.0, 3.0), // lookfrom
-                Vec3::zero(), // lookat
-                Vec3::new(0.0, 1.0, 0.0), // vup
-                20.0,
-                aspect_ratio, 
-                0.1, // aperture
-                10.0, // dist_to_focus
-            ),
-            world: Scene::random_world()
-        };
+        let scene = Scene::random_scene(aspect_ratio);
 
         return Self {
-            aspect_ratio: 3.0 / 2.0,
+            aspect_ratio,
             bounds: image,
             render_config,
+            output: OutputSettings::default(),
             scene,
-            running: false,
-            signal_to_stop: false,
+            buffers: Rc::new(RefCell::new(AovBuffers::new())),
+            passes: Rc::new(Cell::new(0)),
+            adaptive_render: None,
+            stats: Rc::new(RefCell::new(RenderStats::default())),
+            stats_overlay: false,
+            controls: Rc::new(RefCell::new(Controls::new(Scene::random_scene_view()))),
+            listeners: None,
+            display_pass: Aov::Color,
+            denoise: None,
+            running: Rc::new(Cell::new(false)),
+            signal_to_stop: Rc::new(Cell::new(false)),
         }
     }
 
     pub fn start(&mut self, canvas_target: String) {
-        // set running, clear signal (in case of restarting)
-        self.running = true;
-        self.signal_to_stop = false;
-        let pixel_bytes = self.gogo();
-        let window = web_sys::window().unwrap();
-        let document = window.document().expect("Could not get document");
-        let canvas = document
-            .get_element_by_id(&canvas_target)
-            .unwrap()
-            .dyn_into::<web_sys::HtmlCanvasElement>()
-            .expect("Could not get HTML Canvas Element");
-        let context = canvas
-            .get_context("2d")
-            .expect("Could not get CanvasRenderingContext2d")
-            .unwrap()
-            .dyn_into::<web_sys::CanvasRenderingContext2d>()
-            .expect("Could not convert CanvasRenderingContext2d into a dyn");
-        let clamped_buf: Clamped<&[u8]> = Clamped(&pixel_bytes);
-        let image_data_temp = ImageData::new_with_u8_clamped_array_and_sh(
-            clamped_buf,
-            self.bounds.x as u32,
-            self.bounds.y as u32,
-        ).expect("Could not create temporary ImageData from byte array");
-        context.put_image_data(&image_data_temp, 0.0, 0.0)
-            .expect("Could not put image on canvas element");
+        self.stop(); // this one replaces an async render that's still going
+        self.apply_controls();
+        let (buffers, stats) = self.gogo();
+        *self.buffers.borrow_mut() = buffers;
+        *self.stats.borrow_mut() = stats;
+        self.passes.set(self.render_config.samples);
+        self.redraw(canvas_target);
+    }
+
+    /*
+     * Like `start()`, but hands control back to the browser between tiles
+     * (every animation frame), showing the color as it fills in. The promise
+     * resolves with the render stats (as `stats()`) once the image is done, or rejects if
+     * `stop()` (or another render) gets there first.
+     */
+    pub fn render_async(&mut self, canvas_target: String) -> js_sys::Promise {
+        self.stop();
+        self.apply_controls();
+        self.signal_to_stop = Rc::new(Cell::new(false));
+        self.running.set(true);
+        let render = AsyncRender {
+            canvas_target,
+            size: self.bounds,
+            scene: self.scene.clone(),
+            properties: self.render_config.clone(),
+            output: self.output,
+            display_pass: self.display_pass,
+            denoise: self.denoise,
+            buffers: self.buffers.clone(),
+            passes: self.passes.clone(),
+            stats: self.stats.clone(),
+            stats_overlay: self.stats_overlay,
+            running: self.running.clone(),
+            cancelled: self.signal_to_stop.clone(),
+        };
+        return wasm_bindgen_futures::future_to_promise(render.run());
+    }
+
+    /*
+     * Lets the mouse and touch move the camera around: drag to orbit, right
+     * or shift drag (two fingers) to pan, wheel (pinch) to zoom, and click
+     * (tap) to focus. The changes show up on the next `step()`.
+     */
+    pub fn attach_controls(&mut self, canvas_target: String) {
+        let canvas = get_canvas(&canvas_target);
+        self.listeners = Some(Listeners::attach(canvas, self.controls.clone()));
+    }
+
+    pub fn detach_controls(&mut self) {
+        self.listeners = None;
+    }
+
+    /*
+     * One step of progressive rendering, meant to be called every animation
+     * frame. While the camera is dragged around it shows a quick low
+     * resolution preview, otherwise it adds a sample per pixel to the image
+     * until there are `samples` of them. With adaptive sampling, which can't
+     * be split into passes like that, it renders for a frame's worth of time
+     * instead. Moving the camera starts over.
+     *
+     * Returns how many samples per pixel the displayed image has.
+     */
+    pub fn step(&mut self, canvas_target: String) -> u32 {
+        self.apply_controls();
+        let passes = self.passes.get();
+        if self.running.get() {
+            return passes; // the async render has the canvas
+        }
+        if self.controls.borrow().is_dragging() {
+            let pixels = self.preview();
+            let pixel_bytes = self.output.to_rgba8(&pixels, self.bounds.x);
+            put_image(&canvas_target, self.bounds, &pixel_bytes);
+            return 0;
+        }
+        if passes >= self.render_config.samples {
+            return passes; // done, nothing to draw
+        }
+        if let Some(adaptive) = self.render_config.adaptive {
+            return self.step_adaptive(canvas_target, &adaptive);
+        }
+        let properties = RenderProperties {
+            samples: 1,
+            ..self.render_config.clone()
+        };
+        let (pass, pass_stats) = renderer::render_pass_with_stats(self.bounds, &self.scene, &properties, passes);
+        renderer::accumulate(&mut self.buffers.borrow_mut(), pass, passes);
+        if passes == 0 {
+            *self.stats.borrow_mut() = pass_stats;
+        } else {
+            self.stats.borrow_mut().merge(&pass_stats);
+        }
+        self.passes.set(passes + 1);
+        self.redraw(canvas_target);
+        return passes + 1;
+    }
+
+    /*
+     * What's under the canvas pixel `(x, y)`, top left is `(0, 0)`: the
+     * object's index in the scene, its material, and where the ray through
+     * the pixel hit it. `undefined` if it hit nothing.
+     */
+    pub fn pick(&self, x: i32, y: i32) -> Option<Pick> {
+        if x < 0 || y < 0 || x >= self.bounds.x || y >= self.bounds.y {
+            return None;
+        }
+        return pick::pick(&self.scene, Vec2i::new(x, y), self.bounds);
+    }
+
+    /*
+     * Scene editing. Objects are addressed by their ID, which doesn't change
+     * when others are removed; `object_id()` and `object_index()` convert
+     * from and to positions in the list (as `pick()` returns). Every edit
+     * starts the progressive rendering over.
+     */
+    pub fn object_count(&self) -> u32 {
+        self.scene.object_count() as u32
+    }
+
+    pub fn object_id(&self, index: u32) -> Option<u32> {
+        self.scene.object_id(index as usize)
+    }
+
+    pub fn object_index(&self, id: u32) -> Option<u32> {
+        self.scene.object_index(id).map(|index| index as u32)
+    }
+
+    // Returns the new sphere's ID
+    pub fn add_sphere(&mut self, x: Float, y: Float, z: Float, radius: Float, material: &MaterialParams) -> u32 {
+        self.restart();
+        return self.scene.add_sphere(Vec3::new(x, y, z), radius, material.material());
+    }
+
+    // False if there's no object with that ID
+    pub fn remove_object(&mut self, id: u32) -> bool {
+        let changed = self.scene.remove_object(id);
+        return self.edited(changed);
+    }
+
+    // Moves and resizes a sphere. False if there's no sphere with that ID.
+    pub fn set_sphere(&mut self, id: u32, x: Float, y: Float, z: Float, radius: Float) -> bool {
+        let changed = self.scene.set_sphere(id, Vec3::new(x, y, z), radius);
+        return self.edited(changed);
+    }
+
+    // False if there's no object with that ID
+    pub fn set_material(&mut self, id: u32, material: &MaterialParams) -> bool {
+        let changed = self.scene.set_material(id, material.material());
+        return self.edited(changed);
+    }
+
+    /*
+     * The material library. Objects refer to its entries by handle (see
+     * `Pick::material_handle`), so editing an entry changes every object
+     * using it. Entries have unique single-word names and are never removed.
+     */
+    pub fn material_count(&self) -> u32 {
+        self.scene.materials.len() as u32
+    }
+
+    pub fn material_name(&self, handle: u32) -> Option<String> {
+        self.scene.materials.get(handle).map(|entry| entry.name.clone())
+    }
+
+    pub fn material_handle(&self, name: &str) -> Option<u32> {
+        self.scene.materials.handle(name)
+    }
+
+    // Returns the new entry's handle, throws if the name is taken or not a single word
+    pub fn add_material(&mut self, name: &str, material: &MaterialParams) -> Result<u32, String> {
+        return self.scene.materials.add(name, material.material());
+    }
+
+    // For every object using the entry. False if there's no such entry.
+    pub fn set_library_material(&mut self, handle: u32, material: &MaterialParams) -> bool {
+        let changed = self.scene.materials.set(handle, material.material());
+        return self.edited(changed);
+    }
+
+    pub fn rename_material(&mut self, handle: u32, name: &str) -> Result<(), String> {
+        return self.scene.materials.rename(handle, name);
+    }
+
+    // False if there's no object with that ID or no entry with that handle
+    pub fn set_material_handle(&mut self, id: u32, handle: u32) -> bool {
+        let changed = self.scene.set_material_handle(id, handle);
+        return self.edited(changed);
+    }
+
+    // Like `add_sphere`, with a library entry. `undefined` if there's no such entry.
+    pub fn add_sphere_with(&mut self, x: Float, y: Float, z: Float, radius: Float, handle: u32) -> Option<u32> {
+        let id = self.scene.add_sphere_with(Vec3::new(x, y, z), radius, handle);
+        self.edited(id.is_some());
+        return id;
+    }
+
+    // The materials and objects in the text format of `scene_file`, without the camera
+    pub fn export_scene(&self) -> String {
+        scene_file::write(&self.scene)
+    }
+
+    // Replaces the materials and objects, keeps the camera. Throws with the line that's wrong.
+    pub fn import_scene(&mut self, text: &str) -> Result<(), String> {
+        self.scene = scene_file::read(self.scene.camera, text)?;
+        self.restart();
+        return Ok(());
+    }
+
+    // Starts the progressive rendering over, for after changing the render settings
+    pub fn restart(&mut self) {
+        self.passes.set(0);
+        self.adaptive_render = None;
+    }
+
+    // Pushes the last render through the output stage again (e.g. after changing the exposure)
+    pub fn redraw(&self, canvas_target: String) {
+        let Some(pixels) = self.display_buffer() else {
+            return; // nothing rendered yet
+        };
+        let pixel_bytes = display_bytes(&pixels, self.display_pass, &self.output, self.bounds);
+        put_image(&canvas_target, self.bounds, &pixel_bytes);
+        if self.stats_overlay {
+            draw_stats(&canvas_target, &self.stats.borrow());
+        }
+    }
+
+    /*
+     * Counters and timings of the image shown: `pixels`,
+     * `samples_per_pixel`, `primary_rays`, `rays`, `intersection_tests`,
+     * `average_path_length`, `rays_per_second`, `milliseconds`, `tiles` and
+     * `tile_milliseconds`.
+     * Progressive passes add up.
+     */
+    pub fn stats(&self) -> JsValue {
+        self.stats.borrow().to_js()
+    }
+
+    // Draws a summary of `stats()` over the image
+    pub fn set_stats_overlay(&mut self, enabled: bool) {
+        self.stats_overlay = enabled;
+    }
+
+    // Exposure adjustment in stops, applied before tone mapping
+    pub fn set_exposure(&mut self, stops: Float) {
+        self.output.exposure = stops;
     }
 
+    pub fn set_tone_mapper(&mut self, tone_mapper: ToneMapper) {
+        self.output.tone_mapper = tone_mapper;
+    }
+
+    pub fn set_dither(&mut self, dither: bool) {
+        self.output.dither = dither;
+    }
+
+    // Runs the denoiser over the color pass before showing or exporting it
+    pub fn set_denoise(&mut self, enabled: bool) {
+        self.denoise = if enabled { Some(DenoiseSettings::default()) } else { None };
+    }
+
+    // Which render pass `redraw()` shows and the exporters write
+    pub fn set_display_pass(&mut self, pass: Aov) {
+        self.display_pass = pass;
+    }
+
+    /*
+     * Spend the samples where the image is noisy, until the relative error of
+     * every pixel is below `threshold`. Zero or less goes back to uniform sampling.
+     */
+    pub fn set_adaptive(&mut self, threshold: Float) {
+        self.render_config.adaptive = if threshold <= 0.0 {
+            None
+        } else {
+            Some(AdaptiveSampling {
+                min_samples: 4,
+                max_samples: self.render_config.samples * 8,
+                threshold,
+            })
+        };
+    }
+
+    // Path tracing or one of the quick previews
+    pub fn set_integrator(&mut self, integrator: IntegratorKind) {
+        self.render_config.integrator.kind = integrator;
+    }
+
+    // Distance within which geometry occludes, for the ambient occlusion preview
+    pub fn set_ao_radius(&mut self, radius: Float) {
+        self.render_config.integrator.ao_radius = radius;
+    }
+
+    // What shows up white in the hit distance and intersection count heatmaps
+    pub fn set_heatmap_range(&mut self, max_distance: Float, max_tests: u32) {
+        self.render_config.integrator.max_distance = max_distance;
+        self.render_config.integrator.max_tests = max_tests;
+    }
+
+    // Projection of the camera, panoramas look best on a 2:1 canvas
+    pub fn set_camera_model(&mut self, model: CameraModel) {
+        let mut controls = self.controls.borrow_mut();
+        controls.view.model = model;
+        controls.changed = true;
+    }
+
+    // Sample sequence for the pixel, lens and bounce decisions
+    pub fn set_sampler(&mut self, sampler: Sampler) {
+        self.render_config.sampler = sampler;
+    }
+
+    // Pixel reconstruction filter, a `radius` of zero or less picks the filter's usual one
+    pub fn set_filter(&mut self, filter: Filter, radius: Float) {
+        self.render_config.filter = PixelFilter::new(filter, radius);
+    }
+
+    // Trace `wavelengths` wavelengths per sample instead of RGB. Zero goes back to RGB.
+    pub fn set_spectral(&mut self, wavelengths: u32) {
+        self.render_config.color_mode = if wavelengths == 0 {
+            ColorMode::Rgb
+        } else {
+            ColorMode::Spectral { wavelengths }
+        };
+    }
+
+    // The displayed pass of the last render as a Radiance `.hdr` file, for downloading from JS
+    pub fn export_hdr(&self) -> Vec<u8> {
+        let Some(pixels) = self.display_buffer() else {
+            return Vec::new(); // nothing rendered yet
+        };
+        hdr::encode_radiance_hdr(&pixels, self.bounds.x as usize, self.bounds.y as usize)
+    }
+
+    // The displayed pass of the last render as an OpenEXR file, for downloading from JS
+    pub fn export_exr(&self, pixel_type: ExrPixelType, compression: ExrCompression) -> Vec<u8> {
+        let Some(pixels) = self.display_buffer() else {
+            return Vec::new(); // nothing rendered yet
+        };
+        hdr::encode_openexr(
+            &pixels,
+            self.bounds.x as usize,
+            self.bounds.y as usize,
+            pixel_type,
+            compression,
+        )
+    }
+
+    // Cancels the async render going, if any
     pub fn stop(&mut self) {
-        self.signal_to_stop = true;
+        self.signal_to_stop.set(true);
+        self.running.set(false);
     }
 
     pub fn is_ready() -> bool {
@@ -105,30 +479,175 @@ impl Renderer {
     }
 }
 
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Renderer {
-    // main rendering loop.
-    fn gogo(&self) -> Vec<u8> {
-        let mut pixel_bytes: Vec<u8> = Vec::new();
-        for row in (0..self.bounds.y).rev() {
-            let tile = Tile::render_line(row, self.bounds, &self.scene, &self.render_config);
-            for pixel in tile.pixels {
-                
-                
-                // gamma correction
-                let scale = 1.0 / self.render_config.samples as f32;
-                let r = (pixel.x * scale).sqrt();
-                let g = (pixel.y * scale).sqrt();
-                let b = (pixel.z * scale).sqrt();
-                pixel_bytes.push((r * 255.0) as u8);
-                pixel_bytes.push((g * 255.0) as u8);
-                pixel_bytes.push((b * 255.0) as u8);
-                pixel_bytes.push(255); // dummy alpha value to make ImageData happy
+    // The pass selected for display, denoised if that's turned on and it's the color
+    fn display_buffer(&self) -> Option<Vec<Vec3>> {
+        display_pixels(&self.buffers.borrow(), self.display_pass, self.denoise.as_ref(), self.bounds)
+    }
+
+    // Restarts the progressive rendering if the scene changed
+    fn edited(&mut self, changed: bool) -> bool {
+        if changed {
+            self.restart();
+        }
+        return changed;
+    }
+
+    /*
+     * Picks up what the controls did since the last time: focuses on what
+     * was clicked, and rebuilds the camera if the view changed. The
+     * progressive rendering starts over then.
+     */
+    fn apply_controls(&mut self) {
+        let mut controls = self.controls.borrow_mut();
+        if let Some(position) = controls.focus_request.take() {
+            let ray = self.scene.camera.get_pinhole_ray(position.x, position.y);
+            if let Some(hit) = self.scene.world.hit(ray, 0.0, Float::INFINITY) {
+                controls.view.focus_dist = self.scene.camera.view_depth(hit.p);
+                controls.changed = true;
             }
         }
-        return pixel_bytes;
+        if !controls.changed {
+            return;
+        }
+        controls.changed = false;
+        self.scene.camera = controls.view.camera(self.aspect_ratio);
+        self.passes.set(0);
+        self.adaptive_render = None;
+    }
+
+    // `step()` with adaptive sampling, returns the average samples per pixel so far
+    fn step_adaptive(&mut self, canvas_target: String, adaptive: &AdaptiveSampling) -> u32 {
+        let mut render = self.adaptive_render.take()
+            .unwrap_or_else(|| AdaptiveRender::new(self.bounds, &self.render_config, adaptive));
+        let slice_start = stats::now_ms();
+        let mut more = true;
+        while more && stats::now_ms() - slice_start < FRAME_BUDGET_MS {
+            more = render.render_next(&self.scene);
+        }
+        if more {
+            let samples = render.samples_per_pixel();
+            let pixel_bytes = self.output.to_rgba8(&render.color(), self.bounds.x);
+            put_image(&canvas_target, self.bounds, &pixel_bytes);
+            self.adaptive_render = Some(render);
+            return samples;
+        }
+        let (buffers, stats) = render.finish();
+        *self.buffers.borrow_mut() = buffers;
+        *self.stats.borrow_mut() = stats;
+        self.passes.set(self.render_config.samples);
+        self.redraw(canvas_target);
+        return self.render_config.samples;
+    }
+
+    // One sample per pixel at a fraction of the resolution, scaled back up
+    fn preview(&self) -> Vec<Vec3> {
+        let size = Vec2i::new(
+            (self.bounds.x / PREVIEW_SCALE).max(2),
+            (self.bounds.y / PREVIEW_SCALE).max(2),
+        );
+        let properties = RenderProperties {
+            samples: 1,
+            aovs: Vec::new(),
+            adaptive: None,
+            ..self.render_config.clone()
+        };
+        let small = &renderer::render_image(size, &self.scene, &properties)[&Aov::Color];
+        let mut pixels = Vec::with_capacity((self.bounds.x * self.bounds.y) as usize);
+        for y in 0..self.bounds.y {
+            let row = (y * size.y / self.bounds.y) * size.x;
+            pixels.extend((0..self.bounds.x).map(|x| small[(row + x * size.x / self.bounds.x) as usize]));
+        }
+        return pixels;
     }
+
+    // main rendering loop. Produces the averaged, linear radiance (and AOVs) of every pixel.
+    fn gogo(&self) -> (AovBuffers, RenderStats) {
+        return renderer::render_image_with_stats(self.bounds, &self.scene, &self.render_config);
+    }
+}
+
+// The pass selected for display, denoised if that's turned on and it's the color
+fn display_pixels(buffers: &AovBuffers, pass: Aov, denoise: Option<&DenoiseSettings>, size: Vec2i) -> Option<Vec<Vec3>> {
+    let pixels = buffers.get(&pass)?;
+    let (Aov::Color, Some(settings)) = (pass, denoise) else {
+        return Some(pixels.clone());
+    };
+    let guides = Guides {
+        albedo: buffers.get(&Aov::Albedo)?,
+        normal: buffers.get(&Aov::Normal)?,
+    };
+    let denoised = denoise::denoise(
+        pixels,
+        &guides,
+        size.x as usize,
+        size.y as usize,
+        settings,
+    );
+    return Some(denoised);
+}
+
+// The color goes through the output stage, the other passes get visualized
+fn display_bytes(pixels: &[Vec3], pass: Aov, output: &OutputSettings, size: Vec2i) -> Vec<u8> {
+    if pass == Aov::Color {
+        return output.to_rgba8(pixels, size.x);
+    }
+    return output::display_to_rgba8(&pass.visualize(pixels));
+}
+
+fn get_context(canvas_target: &str) -> CanvasRenderingContext2d {
+    let canvas = get_canvas(canvas_target);
+    return canvas
+        .get_context("2d")
+        .expect("Could not get CanvasRenderingContext2d")
+        .unwrap()
+        .dyn_into::<CanvasRenderingContext2d>()
+        .expect("Could not convert CanvasRenderingContext2d into a dyn");
+}
+
+fn put_image(canvas_target: &str, size: Vec2i, pixel_bytes: &[u8]) {
+    let context = get_context(canvas_target);
+    let clamped_buf: Clamped<&[u8]> = Clamped(pixel_bytes);
+    let image_data_temp = ImageData::new_with_u8_clamped_array_and_sh(
+        clamped_buf,
+        size.x as u32,
+        size.y as u32,
+    ).expect("Could not create temporary ImageData from byte array");
+    context.put_image_data(&image_data_temp, 0.0, 0.0)
+        .expect("Could not put image on canvas element");
+}
+
+// The stats summary in the top left corner, on a dark band to stay readable
+fn draw_stats(canvas_target: &str, stats: &RenderStats) {
+    const LINE_HEIGHT: f64 = 10.0;
+    let context = get_context(canvas_target);
+    let lines = stats.summary();
+    context.set_fill_style_str("rgba(0, 0, 0, 0.6)");
+    context.fill_rect(0.0, 0.0, context.canvas().map_or(0.0, |canvas| canvas.width() as f64), LINE_HEIGHT * lines.len() as f64 + 4.0);
+    context.set_fill_style_str("white");
+    context.set_font("8px monospace");
+    for (idx, line) in lines.iter().enumerate() {
+        // nothing useful to do if it fails, the image is still there
+        let _ = context.fill_text(line, 2.0, LINE_HEIGHT * (idx + 1) as f64);
+    }
+}
+
+fn get_canvas(canvas_target: &str) -> HtmlCanvasElement {
+    let window = web_sys::window().unwrap();
+    let document = window.document().expect("Could not get document");
+    return document
+        .get_element_by_id(canvas_target)
+        .unwrap()
+        .dyn_into::<HtmlCanvasElement>()
+        .expect("Could not get HTML Canvas Element");
 }
 
-pub (crate) fn lerp(range: Vec2f, value: f32) -> f32 {
+pub (crate) fn lerp(range: Vec2f, value: Float) -> Float {
     return (1.0 - value) * range.x + value * range.y
 }
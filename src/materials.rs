@@ -0,0 +1,133 @@
+/*
+ * The scene's material library.
+ *
+ * Objects refer to their material by handle (its index here), so any
+ * number of them can share one, and editing an entry changes all of them
+ * at once ("make all the glass a bit bluer"). Entries have unique names,
+ * which is how scene files refer to them; they're never removed, so
+ * handles stay valid.
+ */
+
+use crate::scene::Material;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialEntry {
+    pub name: String,
+    pub material: Material,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MaterialLibrary {
+    entries: Vec<MaterialEntry>,
+}
+
+// Names are single words, so they fit in the scene file format
+fn check_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.contains(|c: char| c.is_whitespace() || c == '#') {
+        return Err(format!("Invalid material name: {:?}", name));
+    }
+    return Ok(());
+}
+
+impl MaterialLibrary {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[MaterialEntry] {
+        &self.entries
+    }
+
+    pub fn get(&self, handle: u32) -> Option<&MaterialEntry> {
+        self.entries.get(handle as usize)
+    }
+
+    // For the hits, which only get valid handles
+    pub fn material(&self, handle: u32) -> Material {
+        self.entries[handle as usize].material
+    }
+
+    pub fn handle(&self, name: &str) -> Option<u32> {
+        self.entries.iter().position(|entry| entry.name == name).map(|index| index as u32)
+    }
+
+    // Returns the new entry's handle, fails if the name is taken or invalid
+    pub fn add(&mut self, name: &str, material: Material) -> Result<u32, String> {
+        check_name(name)?;
+        if self.handle(name).is_some() {
+            return Err(format!("There already is a material called {}", name));
+        }
+        self.entries.push(MaterialEntry { name: name.to_string(), material });
+        return Ok(self.entries.len() as u32 - 1);
+    }
+
+    /*
+     * The handle of an entry with exactly this material, or of a new one
+     * named after its kind ("metal", "metal_2", ...). For scenes built
+     * without names.
+     */
+    pub fn find_or_add(&mut self, material: Material) -> u32 {
+        if let Some(index) = self.entries.iter().position(|entry| entry.material == material) {
+            return index as u32;
+        }
+        let kind = material.name();
+        let name = (1..)
+            .map(|count| if count == 1 { kind.to_string() } else { format!("{}_{}", kind, count) })
+            .find(|name| self.handle(name).is_none())
+            .expect("Ran out of names");
+        self.entries.push(MaterialEntry { name, material });
+        return self.entries.len() as u32 - 1;
+    }
+
+    // Changes the material of everything using the entry. False if there's no such entry.
+    pub fn set(&mut self, handle: u32, material: Material) -> bool {
+        let Some(entry) = self.entries.get_mut(handle as usize) else {
+            return false;
+        };
+        entry.material = material;
+        return true;
+    }
+
+    pub fn rename(&mut self, handle: u32, name: &str) -> Result<(), String> {
+        check_name(name)?;
+        match self.handle(name) {
+            Some(existing) if existing == handle => return Ok(()),
+            Some(_) => return Err(format!("There already is a material called {}", name)),
+            None => {},
+        }
+        let entry = self.entries.get_mut(handle as usize).ok_or(format!("No material with handle {}", handle))?;
+        entry.name = name.to_string();
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::primitives::Vec3;
+
+    #[test]
+    fn test_names_and_sharing() {
+        let mut library = MaterialLibrary::default();
+        let glass = Material::Dielectric { index_refraction: 1.5, dispersion: 0.0 };
+        let first = library.find_or_add(glass);
+        assert_eq!(library.find_or_add(glass), first);
+        let tinted = Material::Dielectric { index_refraction: 1.3, dispersion: 0.0 };
+        let second = library.find_or_add(tinted);
+        assert_eq!(library.get(second).unwrap().name, "dielectric_2");
+
+        assert!(library.rename(first, "glass").is_ok());
+        assert!(library.rename(second, "glass").is_err());
+        assert!(library.add("two words", glass).is_err());
+        assert_eq!(library.handle("glass"), Some(first));
+
+        let diamond = Material::Dielectric { index_refraction: 2.4, dispersion: 0.01 };
+        assert!(library.set(first, diamond));
+        assert_eq!(library.material(first), diamond);
+        assert_eq!(library.add("mirror", Material::Metal { albedo: Vec3::ones(), fuzz: 0.0 }), Ok(2));
+    }
+}
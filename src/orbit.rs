@@ -0,0 +1,122 @@
+/*
+ * A camera that orbits around a target point, for the interactive controls.
+ *
+ * `Camera` only keeps what `get_ray()` needs, so this is what gets edited
+ * and a new `Camera` is built from it after every change.
+ */
+
+use crate::primitives::{Float, Vec3, consts};
+use crate::scene::{Camera, CameraModel};
+
+// keeps the view from flipping over the poles
+const MAX_PITCH: Float = 89.0 * consts::PI / 180.0;
+const MIN_DISTANCE: Float = 0.01;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrbitCamera {
+    pub target: Vec3,
+    pub distance: Float,
+    pub yaw: Float,   // radians around the world up axis, 0 looks down -z
+    pub pitch: Float, // radians above the horizon of the target
+    pub vfov: Float,  // degrees
+    pub aperture: Float,
+    pub focus_dist: Float,
+    pub model: CameraModel,
+}
+
+impl OrbitCamera {
+    pub fn looking_at(lookfrom: Vec3, lookat: Vec3, vfov: Float, aperture: Float, focus_dist: Float) -> OrbitCamera {
+        let offset = lookfrom - lookat;
+        let distance = offset.length().max(MIN_DISTANCE);
+        OrbitCamera {
+            target: lookat,
+            distance,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).clamp(-1.0, 1.0).asin(),
+            vfov,
+            aperture,
+            focus_dist,
+            model: CameraModel::Perspective,
+        }
+    }
+
+    pub fn lookfrom(&self) -> Vec3 {
+        let offset = Vec3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        );
+        self.target + offset * self.distance
+    }
+
+    pub fn camera(&self, aspect_ratio: Float) -> Camera {
+        Camera::new(
+            self.lookfrom(),
+            self.target,
+            Vec3::new(0.0, 1.0, 0.0),
+            self.vfov,
+            aspect_ratio,
+            self.aperture,
+            self.focus_dist,
+        ).with_model(self.model)
+    }
+
+    // Turns around the target, angles in radians
+    pub fn orbit(&mut self, yaw: Float, pitch: Float) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /*
+     * Moves the camera and the target sideways. `right` and `up` are in
+     * fractions of the view height at the target, so the scene follows the
+     * pointer.
+     */
+    pub fn pan(&mut self, right: Float, up: Float) {
+        let forward = Vec3::as_unit(self.target - self.lookfrom());
+        let right_dir = Vec3::as_unit(Vec3::cross(forward, Vec3::new(0.0, 1.0, 0.0)));
+        let up_dir = Vec3::cross(right_dir, forward);
+        let view_height = 2.0 * self.distance * (self.vfov.to_radians() / 2.0).tan();
+        self.target += (right_dir * right + up_dir * up) * view_height;
+    }
+
+    // Moves toward the target (`factor` below 1) or away from it. The focus moves along.
+    pub fn zoom(&mut self, factor: Float) {
+        let distance = (self.distance * factor).max(MIN_DISTANCE);
+        self.focus_dist = (self.focus_dist + distance - self.distance).max(MIN_DISTANCE);
+        self.distance = distance;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_looking_at_round_trip() {
+        let view = OrbitCamera::looking_at(Vec3::new(13.0, 2.0, 3.0), Vec3::zero(), 20.0, 0.1, 10.0);
+        assert!((view.lookfrom() - Vec3::new(13.0, 2.0, 3.0)).near_zero());
+    }
+
+    #[test]
+    fn test_orbit_keeps_distance() {
+        let mut view = OrbitCamera::looking_at(Vec3::new(1.0, 0.0, 5.0), Vec3::new(1.0, 0.0, 0.0), 40.0, 0.0, 5.0);
+        view.orbit(consts::FRAC_PI_2, 0.0);
+        assert!((view.lookfrom() - Vec3::new(6.0, 0.0, 0.0)).near_zero());
+        view.orbit(0.0, 10.0);
+        assert_eq!(view.pitch, MAX_PITCH);
+        assert!(((view.lookfrom() - view.target).length() - 5.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_pan_and_zoom() {
+        let mut view = OrbitCamera::looking_at(Vec3::new(0.0, 0.0, 5.0), Vec3::zero(), 90.0, 0.0, 5.0);
+        view.pan(0.5, 0.0);
+        // the view is 10 high at the target, so half of it is 5 to the right
+        assert!((view.target - Vec3::new(5.0, 0.0, 0.0)).near_zero());
+        assert!((view.lookfrom() - Vec3::new(5.0, 0.0, 5.0)).near_zero());
+        view.zoom(0.5);
+        assert_eq!(view.distance, 2.5);
+        assert_eq!(view.focus_dist, 2.5);
+    }
+}
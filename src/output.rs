@@ -0,0 +1,220 @@
+use wasm_bindgen::prelude::*;
+
+use crate::primitives::{Float, Vec2i, Vec3};
+
+// Curves for squeezing linear radiance into the displayable 0..1 range
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ToneMapper {
+    Clamp,
+    Reinhard,
+    AcesFilmic,
+}
+
+// 4x4 Bayer matrix for ordered dithering
+const BAYER_4X4: [[Float; 4]; 4] = [
+    [ 0.0,  8.0,  2.0, 10.0],
+    [12.0,  4.0, 14.0,  6.0],
+    [ 3.0, 11.0,  1.0,  9.0],
+    [15.0,  7.0, 13.0,  5.0],
+];
+
+/*
+ * Output stage: converts linear radiance into 8-bit sRGB.
+ *
+ * exposure (in stops) -> tone mapping -> sRGB transfer -> (dithered) quantization
+ */
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OutputSettings {
+    pub exposure: Float, // in stops, so 1.0 doubles the brightness
+    pub tone_mapper: ToneMapper,
+    pub dither: bool,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tone_mapper: ToneMapper::Clamp,
+            dither: true,
+        }
+    }
+}
+
+fn luminance(c: Vec3) -> Float {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
+// Narkowicz's fit of the ACES reference rendering transform
+fn aces_filmic(x: Float) -> Float {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    (x * (a * x + b)) / (x * (c * x + d) + e)
+}
+
+// The sRGB transfer function ("gamma"), including the linear toe
+pub fn srgb_encode(linear: Float) -> Float {
+    if linear <= 0.0031308 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl ToneMapper {
+    // maps linear color into 0..1 (still linear)
+    pub fn apply(&self, color: Vec3) -> Vec3 {
+        let mapped = match self {
+            ToneMapper::Clamp => color,
+            ToneMapper::Reinhard => {
+                // operate on luminance so saturated colors keep their hue
+                let lum = luminance(color);
+                if lum <= 0.0 {
+                    color
+                } else {
+                    color * (1.0 / (1.0 + lum))
+                }
+            },
+            ToneMapper::AcesFilmic => Vec3 {
+                x: aces_filmic(color.x),
+                y: aces_filmic(color.y),
+                z: aces_filmic(color.z),
+            },
+        };
+        Vec3 {
+            x: mapped.x.clamp(0.0, 1.0),
+            y: mapped.y.clamp(0.0, 1.0),
+            z: mapped.z.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl OutputSettings {
+    // Tone mapped, sRGB encoded color that is still in floating point (0..1)
+    pub fn display_color(&self, linear: Vec3) -> Vec3 {
+        // NaNs would otherwise survive all the way to the `as u8` cast
+        let linear = if linear.x.is_nan() || linear.y.is_nan() || linear.z.is_nan() {
+            Vec3::zero()
+        } else {
+            linear
+        };
+        let mapped = self.tone_mapper.apply(linear * self.exposure.exp2());
+        Vec3 {
+            x: srgb_encode(mapped.x),
+            y: srgb_encode(mapped.y),
+            z: srgb_encode(mapped.z),
+        }
+    }
+
+    /*
+     * Converts a linear color to 8-bit sRGB. The coordinate is only used to
+     * look up the dither threshold.
+     */
+    pub fn map_pixel(&self, linear: Vec3, coord: Vec2i) -> [u8; 3] {
+        let display = self.display_color(linear);
+        // offset of up to half a step either way, in 1/255ths
+        let offset = if self.dither {
+            let threshold = BAYER_4X4[coord.y.rem_euclid(4) as usize][coord.x.rem_euclid(4) as usize];
+            (threshold + 0.5) / 16.0 - 0.5
+        } else {
+            0.0
+        };
+        let quantize = |c: Float| -> u8 {
+            (c * 255.0 + 0.5 + offset).floor().clamp(0.0, 255.0) as u8
+        };
+        [quantize(display.x), quantize(display.y), quantize(display.z)]
+    }
+
+    // Converts a row-major image of linear colors into RGBA bytes for ImageData
+    pub fn to_rgba8(&self, pixels: &[Vec3], width: i32) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(pixels.len() * 4);
+        for (idx, pixel) in pixels.iter().enumerate() {
+            let coord = Vec2i {
+                x: idx as i32 % width,
+                y: idx as i32 / width,
+            };
+            bytes.extend_from_slice(&self.map_pixel(*pixel, coord));
+            bytes.push(255); // dummy alpha value to make ImageData happy
+        }
+        return bytes;
+    }
+}
+
+/*
+ * RGBA bytes for colors that are already display referred (0..1), like the
+ * AOV visualizations. No tone mapping or transfer function is applied.
+ */
+pub fn display_to_rgba8(pixels: &[Vec3]) -> Vec<u8> {
+    let quantize = |c: Float| -> u8 { (c * 255.0 + 0.5).floor().clamp(0.0, 255.0) as u8 };
+    pixels.iter()
+        .flat_map(|px| [quantize(px.x), quantize(px.y), quantize(px.z), 255])
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn no_dither(tone_mapper: ToneMapper) -> OutputSettings {
+        OutputSettings {
+            exposure: 0.0,
+            tone_mapper,
+            dither: false,
+        }
+    }
+
+    #[test]
+    fn test_srgb_endpoints() {
+        assert_eq!(srgb_encode(0.0), 0.0);
+        assert!((srgb_encode(1.0) - 1.0).abs() < 1e-6);
+        // 18% gray lands just under the middle of the range
+        assert!((srgb_encode(0.18) - 0.461).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_hdr_does_not_wrap() {
+        for mapper in [ToneMapper::Clamp, ToneMapper::Reinhard, ToneMapper::AcesFilmic] {
+            let px = no_dither(mapper).map_pixel(Vec3::new(50.0, 2.0, 1.0), Vec2i::new(0, 0));
+            assert!(px[0] >= px[1] && px[1] >= px[2], "{:?} -> {:?}", mapper, px);
+            assert!(px[0] > 200);
+        }
+    }
+
+    #[test]
+    fn test_clamp_extremes() {
+        let settings = no_dither(ToneMapper::Clamp);
+        assert_eq!(settings.map_pixel(Vec3::zero(), Vec2i::new(0, 0)), [0, 0, 0]);
+        assert_eq!(settings.map_pixel(Vec3::ones(), Vec2i::new(0, 0)), [255, 255, 255]);
+        assert_eq!(settings.map_pixel(-Vec3::ones(), Vec2i::new(0, 0)), [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_exposure_doubles() {
+        let settings = OutputSettings {
+            exposure: 1.0,
+            ..no_dither(ToneMapper::Clamp)
+        };
+        let brighter = settings.display_color(Vec3::new(0.25, 0.25, 0.25));
+        let expected = no_dither(ToneMapper::Clamp).display_color(Vec3::new(0.5, 0.5, 0.5));
+        assert!((brighter - expected).near_zero());
+    }
+
+    #[test]
+    fn test_dither_averages_out() {
+        // a value between two steps should dither to the right average
+        let settings = OutputSettings::default();
+        let target = 100.25 / 255.0;
+        let linear = {
+            // invert the sRGB curve numerically (it's monotonic)
+            let (mut lo, mut hi): (Float, Float) = (0.0, 1.0);
+            for _ in 0..40 {
+                let mid = (lo + hi) / 2.0;
+                if srgb_encode(mid) < target { lo = mid } else { hi = mid }
+            }
+            lo
+        };
+        let sum: u32 = (0..4).flat_map(|y| (0..4).map(move |x| Vec2i::new(x, y)))
+            .map(|coord| settings.map_pixel(Vec3::new(linear, linear, linear), coord)[0] as u32)
+            .sum();
+        assert!((sum as Float / 16.0 - 100.25).abs() < 0.1);
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * Finding out what's under a pixel, for inspecting and editing the scene.
+ *
+ * The ray goes through the center of the pixel and the center of the lens,
+ * so the answer doesn't depend on the sample or the depth of field.
+ */
+
+use wasm_bindgen::prelude::*;
+
+use crate::primitives::{Float, Vec2f, Vec2i, Vec3};
+use crate::renderer;
+use crate::scene::{Material, Scene};
+
+// What the primary ray through a pixel hit first
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug)]
+pub struct Pick {
+    object_index: u32, // index into the scene's object list
+    object_id: u32, // stable ID of the object, see `Scene`
+    material_handle: u32, // index into the scene's material library
+    material: Material,
+    point: Vec3,
+    normal: Vec3, // facing the camera
+    distance: Float, // along the ray, from the camera
+}
+
+// `pixel` is in canvas coordinates, the top row is 0
+pub fn pick(scene: &Scene, pixel: Vec2i, img_size: Vec2i) -> Option<Pick> {
+    let coord = Vec2i::new(pixel.x, img_size.y - 1 - pixel.y);
+    let uv = renderer::to_uv(coord, Vec2f::new(0.5, 0.5), img_size);
+    let ray = scene.camera.get_pinhole_ray(uv.x, uv.y);
+    let hit = scene.world.hit(ray, 0.0, Float::INFINITY)?;
+    return Some(Pick {
+        object_index: hit.object_index,
+        object_id: scene.object_id(hit.object_index as usize)?,
+        material_handle: hit.material,
+        material: scene.material(hit.material),
+        point: hit.p,
+        normal: hit.normal,
+        distance: hit.t * ray.dir.length(),
+    });
+}
+
+impl Pick {
+    pub fn material(&self) -> Material {
+        self.material
+    }
+
+    pub fn point(&self) -> Vec3 {
+        self.point
+    }
+
+    pub fn normal(&self) -> Vec3 {
+        self.normal
+    }
+}
+
+// What JS sees, vectors come out as `[x, y, z]` arrays
+#[wasm_bindgen]
+impl Pick {
+    #[wasm_bindgen(getter)]
+    pub fn object_index(&self) -> u32 {
+        self.object_index
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn object_id(&self) -> u32 {
+        self.object_id
+    }
+
+    // Shared by every object using the same library entry
+    #[wasm_bindgen(getter)]
+    pub fn material_handle(&self) -> u32 {
+        self.material_handle
+    }
+
+    // "lambertian", "metal" or "dielectric"
+    #[wasm_bindgen(getter)]
+    pub fn material_kind(&self) -> String {
+        self.material.name().to_string()
+    }
+
+    // Only for the materials that have one
+    #[wasm_bindgen(getter)]
+    pub fn albedo(&self) -> Option<Vec<Float>> {
+        match self.material {
+            Material::Lambertian { albedo } | Material::Metal { albedo, .. } => Some(vec![albedo.x, albedo.y, albedo.z]),
+            Material::Dielectric { .. } => None,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn fuzz(&self) -> Option<Float> {
+        match self.material {
+            Material::Metal { fuzz, .. } => Some(fuzz),
+            _ => None,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn index_refraction(&self) -> Option<Float> {
+        match self.material {
+            Material::Dielectric { index_refraction, .. } => Some(index_refraction),
+            _ => None,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dispersion(&self) -> Option<Float> {
+        match self.material {
+            Material::Dielectric { dispersion, .. } => Some(dispersion),
+            _ => None,
+        }
+    }
+
+    #[wasm_bindgen(getter = point)]
+    pub fn point_array(&self) -> Vec<Float> {
+        vec![self.point.x, self.point.y, self.point.z]
+    }
+
+    #[wasm_bindgen(getter = normal)]
+    pub fn normal_array(&self) -> Vec<Float> {
+        vec![self.normal.x, self.normal.y, self.normal.z]
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn distance(&self) -> Float {
+        self.distance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scene::{Camera, Hittable};
+
+    #[test]
+    fn test_pick_center() {
+        let mut world = Hittable::HittableList { hittables: Vec::new() };
+        let material = Material::Metal { albedo: Vec3::new(0.8, 0.6, 0.2), fuzz: 0.1 };
+        world.push(Hittable::Sphere { center: Vec3::new(0.0, 0.0, -20.0), radius: 1.0, material });
+        world.push(Hittable::Sphere { center: Vec3::new(0.0, 0.0, -10.0), radius: 5.0, material }); // in front
+        let scene = Scene::new(
+            // a wide open lens, which the pick shouldn't care about
+            Camera::new(Vec3::new(0.0, 0.0, 20.0), Vec3::zero(), Vec3::new(0.0, 1.0, 0.0), 30.0, 1.0, 4.0, 20.0),
+            world,
+        );
+        // the image positions go from the left edge of the first pixel to the
+        // right edge of the last, so in a 5x5 image pixel (2, 2) looks straight ahead
+        let pick = pick(&scene, Vec2i::new(2, 2), Vec2i::new(5, 5)).unwrap();
+        assert_eq!(pick.object_index, 1);
+        assert_eq!(pick.object_id, 1);
+        assert!((pick.point() - Vec3::new(0.0, 0.0, -5.0)).near_zero(), "{:?}", pick.point());
+        assert!((pick.normal() - Vec3::new(0.0, 0.0, 1.0)).near_zero());
+        assert!((pick.distance - 25.0).abs() < 1e-4);
+        assert_eq!(pick.material_kind(), "metal");
+        assert_eq!(pick.material_handle, 0); // both spheres share the one entry
+        assert_eq!(pick.fuzz(), Some(0.1));
+
+        // the top left corner looks past everything
+        assert!(super::pick(&scene, Vec2i::new(0, 0), Vec2i::new(5, 5)).is_none());
+    }
+}
@@ -13,10 +13,27 @@ use std::ops::{
 use std::fmt;
 use std::fmt::Display;
 
-use web_sys::js_sys::Math::random;
+use crate::random::random;
+
+/*
+ * The floating point type of the geometry, the intersection math and the
+ * colors. f32 unless the "f64" feature is on, which is slower but keeps its
+ * precision much further from the origin (acne on the huge ground sphere,
+ * far off cameras). Files and the canvas get f32 or bytes either way.
+ */
+#[cfg(not(feature = "f64"))]
+pub type Float = f32;
+#[cfg(feature = "f64")]
+pub type Float = f64;
+
+// `PI` and friends, as `Float`s
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
 
 pub type Vec2i = Vec2<i32>;
-pub type Vec2f = Vec2<f32>;
+pub type Vec2f = Vec2<Float>;
 
 #[derive (Clone, Copy, PartialEq, PartialOrd, Debug)]
 pub struct Vec2<T>{
@@ -25,19 +42,19 @@ pub struct Vec2<T>{
 }
 
 
-impl Vec2<f32> {
-    pub fn zero() -> Vec2<f32> {
+impl Vec2<Float> {
+    pub fn zero() -> Vec2<Float> {
         Vec2{ x: 0.0, y: 0.0 }
     }
 
-    pub fn ones() -> Vec2<f32> {
+    pub fn ones() -> Vec2<Float> {
         Vec2{ x: 1.0, y: 1.0 }
     }
 
-    pub fn rand(range: Vec2f) -> Vec2<f32> {
+    pub fn rand(range: Vec2f) -> Vec2<Float> {
         Vec2 {
-            x: crate::lerp(range, random() as f32),
-            y: crate::lerp(range, random() as f32)
+            x: crate::lerp(range, random() as Float),
+            y: crate::lerp(range, random() as Float)
         }
     }
 
@@ -69,9 +86,9 @@ where T: std::ops::Mul<Output = T>{
     }
 }
 
-impl Div<f32> for Vec2<f32>{
-    type Output = Vec2<f32>;
-    fn div(self, other: f32) -> Vec2<f32> {
+impl Div<Float> for Vec2<Float>{
+    type Output = Vec2<Float>;
+    fn div(self, other: Float) -> Vec2<Float> {
         Vec2 {
             x: 1.0/other * self.x,
             y: 1.0/other * self.y
@@ -110,13 +127,13 @@ where T: Display { // nested type still needs to have Display
 
 #[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
 pub struct Vec3{
-	pub x: f32,
-	pub y: f32,
-	pub z: f32,
+	pub x: Float,
+	pub y: Float,
+	pub z: Float,
 }
 
 impl Vec3{
-	pub fn new(x: f32, y: f32, z: f32) -> Vec3{
+	pub fn new(x: Float, y: Float, z: Float) -> Vec3{
 		Vec3{x, y, z}
 	}
 
@@ -138,9 +155,9 @@ impl Vec3{
 
     pub fn rand(range: Vec2f) -> Vec3 {
         Vec3{
-            x: crate::lerp(range, random() as f32),
-            y: crate::lerp(range, random() as f32),
-            z: crate::lerp(range, random() as f32),
+            x: crate::lerp(range, random() as Float),
+            y: crate::lerp(range, random() as Float),
+            z: crate::lerp(range, random() as Float),
         }
     }
 
@@ -157,8 +174,8 @@ impl Vec3{
         let distrib = Vec2f::new(-1.0, 1.0);
         loop {
             let p = Vec3 {
-                x: crate::lerp(distrib, random() as f32),
-                y: crate::lerp(distrib, random() as f32),
+                x: crate::lerp(distrib, random() as Float),
+                y: crate::lerp(distrib, random() as Float),
                 z: 0.0,
             };
             if p.length_squared() >= 1.0 { continue; }
@@ -170,32 +187,30 @@ impl Vec3{
         return Vec3::as_unit(Vec3::rand_in_unit_sphere());
     }
 
-	pub fn length(&self) -> f32 {
+	pub fn length(&self) -> Float {
 		self.length_squared().sqrt()
 	}
 
-	pub fn length_squared(&self) -> f32 {
+	pub fn length_squared(&self) -> Float {
 		(self.x * self.x) + (self.y * self.y) + (self.z * self.z)
 	}
     
     // roughly equivalent to the `void write_color(...)` in the book
     pub fn print_ppm(&self, samples_per_pixel: u32) -> String {
+        let scale = 1.0 / samples_per_pixel as Float;
 
-        let scale = 1.0 / samples_per_pixel as f32;
-        
-        // now with gamma correction
-        let r = (self.x * scale).sqrt();
-        let g = (self.y * scale).sqrt();
-        let b = (self.z * scale).sqrt();
-        
-        let ir = (r.clamp( 0.0, 0.999) * 256.0) as i32;
-        let ig = (g.clamp( 0.0, 0.999) * 256.0) as i32;
-        let ib = (b.clamp( 0.0, 0.999) * 256.0) as i32;
+        // same output stage as the canvas, minus the dithering (there's no
+        // pixel coordinate to dither with)
+        let settings = crate::output::OutputSettings {
+            dither: false,
+            ..Default::default()
+        };
+        let [ir, ig, ib] = settings.map_pixel(*self * scale, Vec2i::new(0, 0));
         format!("{} {} {}", ir, ig, ib)
     }
-    
+
     pub fn near_zero(&self) -> bool {
-        let epsilon: f32 = 1e-4;
+        let epsilon: Float = 1e-4;
         return 
             self.x.abs() < epsilon &&
             self.y.abs() < epsilon &&
@@ -206,14 +221,14 @@ impl Vec3{
         return v - n * Vec3::dot(v, n) * 2.0;
     }
     
-    pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f32) -> Vec3 {
+    pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: Float) -> Vec3 {
         let cos_theta = Vec3::dot(-uv, n).min(1.0);
         let r_out_perp = (uv + n * cos_theta) * etai_over_etat;
         let r_out_parallel = n * -(1.0 - r_out_perp.length_squared()).abs().sqrt();
         r_out_perp + r_out_parallel
     }
 
-    pub fn dot(left: Vec3, right: Vec3) -> f32{
+    pub fn dot(left: Vec3, right: Vec3) -> Float{
         left.x * right.x +
         left.y * right.y +
         left.z * right.z
@@ -232,6 +247,10 @@ impl Vec3{
         v / len
     }
 
+    pub fn abs(&self) -> Vec3 {
+        Vec3::new(self.x.abs(), self.y.abs(), self.z.abs())
+    }
+
 }
 impl Add for Vec3 {
 	type Output = Vec3;
@@ -286,9 +305,9 @@ impl Mul<Vec3> for Vec3 {
 	}
 }
 
-impl Mul<f32> for Vec3{
+impl Mul<Float> for Vec3{
 	type Output = Vec3;
-	fn mul(self, other: f32) -> Vec3 {
+	fn mul(self, other: Float) -> Vec3 {
 		Vec3 {
 			x: self.x * other,
 			y: self.y * other,
@@ -307,8 +326,8 @@ impl MulAssign<Vec3> for Vec3 {
 	}
 }
 
-impl MulAssign<f32> for Vec3{
-     fn mul_assign(&mut self, other: f32){
+impl MulAssign<Float> for Vec3{
+     fn mul_assign(&mut self, other: Float){
 		*self = Self {
 			x: self.x * other,
 			y: self.y * other,
@@ -328,9 +347,9 @@ impl Div<Vec3> for Vec3 {
 	}
 }
 
-impl Div<f32> for Vec3 {
+impl Div<Float> for Vec3 {
 	type Output = Vec3;
-	fn div(self, other: f32) -> Vec3 {
+	fn div(self, other: Float) -> Vec3 {
 		Vec3 {
 			x: 1.0/other * self.x,
 			y: 1.0/other * self.y,
@@ -349,8 +368,8 @@ impl DivAssign<Vec3> for Vec3 {
 	}
 }
 
-impl DivAssign<f32> for Vec3 {
-	fn div_assign(&mut self, other: f32){
+impl DivAssign<Float> for Vec3 {
+	fn div_assign(&mut self, other: Float){
 		*self = Self {
 			x: self.x / other,
 			y: self.y / other,
@@ -386,11 +405,45 @@ pub struct Ray{
 }
 
 impl Ray{
-    pub fn at(&self, t: f32) -> Vec3 {
+    pub fn at(&self, t: Float) -> Vec3 {
         self.orig + self.dir*t
     }
 }
 
+/*
+ * Bound on the relative error of `n` floating point operations in a row,
+ * as in PBRT: (1 + e)^n - 1 <= n e / (1 - n e), e being half an ulp of 1.
+ */
+pub fn gamma(n: i32) -> Float {
+    let e = Float::EPSILON * 0.5;
+    (n as Float * e) / (1.0 - n as Float * e)
+}
+
+/*
+ * Where a ray leaving the surface point `p` in direction `w` can start
+ * without hitting the surface it leaves (PBRT's `OffsetRayOrigin`).
+ *
+ * `p` is only known to within `p_error` on each axis, so the true surface
+ * could be anywhere in that box. Moving along the normal `n` far enough to
+ * leave the box (and then rounding away from `p`) puts the origin on the
+ * side `w` points to, however big or small the object and wherever it is.
+ */
+pub fn offset_ray_origin(p: Vec3, p_error: Vec3, n: Vec3, w: Vec3) -> Vec3 {
+    let d = Vec3::dot(n.abs(), p_error);
+    let offset = if Vec3::dot(w, n) < 0.0 { n * -d } else { n * d };
+    let round_away = |po: Float, offset: Float| {
+        if offset > 0.0 {
+            po.next_up()
+        } else if offset < 0.0 {
+            po.next_down()
+        } else {
+            po
+        }
+    };
+    let po = p + offset;
+    return Vec3::new(round_away(po.x, offset.x), round_away(po.y, offset.y), round_away(po.z, offset.z));
+}
+
 #[derive (Copy, Clone)]
 pub struct Rect {
     pub x: i32,
@@ -633,6 +686,7 @@ mod test{
     }
 
     #[test]
+    #[allow(clippy::excessive_precision)] // 1/sqrt(3), more digits than an f32 keeps
     fn test_unit_111(){
         let v = Vec3::new(1.0, 1.0, 1.0);
         let expected = Vec3::new(0.577350269,0.577350269,0.577350269);
@@ -675,6 +729,19 @@ mod test{
         assert!(Vec3::near_zero(&diff));
     }
 
+    #[test]
+    fn test_offset_ray_origin(){
+        let p = Vec3::new(1.0, 1e4, -1.0);
+        let p_error = Vec3::new(0.0, 1e-3, 0.0);
+        let n = Vec3::new(0.0, 1.0, 0.0);
+
+        let up = offset_ray_origin(p, p_error, n, Vec3::new(0.5, 1.0, 0.0));
+        assert!(up.y > p.y + 1e-3);
+        assert_eq!((up.x, up.z), (p.x, p.z));
+        let down = offset_ray_origin(p, p_error, n, Vec3::new(0.5, -1.0, 0.0));
+        assert!(down.y < p.y - 1e-3);
+    }
+
     #[test]
     fn check_lerp(){
         let ray = Ray{
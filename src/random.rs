@@ -0,0 +1,71 @@
+use std::cell::Cell;
+
+/*
+ * Random number source for the tracer.
+ *
+ * JavaScript's `Math.random()` only exists inside the browser, which left the
+ * renderer unusable from native code (CLI, tests). This is a small PCG32
+ * generator with the same interface, so it works everywhere and can be
+ * seeded for reproducible renders.
+ */
+
+const DEFAULT_SEED: u64 = 0x853c49e6748fea9b;
+const PCG_MULTIPLIER: u64 = 6364136223846793005;
+const PCG_INCREMENT: u64 = 1442695040888963407;
+
+thread_local! {
+    static STATE: Cell<u64> = const { Cell::new(DEFAULT_SEED) };
+}
+
+// Restarts the current thread's sequence from `seed`
+pub fn seed(seed: u64) {
+    STATE.with(|state| state.set(seed.wrapping_add(PCG_INCREMENT)));
+    next_u32(); // mix the seed in before the first real output
+}
+
+pub fn next_u32() -> u32 {
+    STATE.with(|state| {
+        let old = state.get();
+        state.set(old.wrapping_mul(PCG_MULTIPLIER).wrapping_add(PCG_INCREMENT));
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    })
+}
+
+/*
+ * Uniform value in [0, 1), like `Math.random()`.
+ *
+ * Only 24 bits are used so the value stays below 1.0 after the `as f32`
+ * casts sprinkled around the tracer.
+ */
+pub fn random() -> f64 {
+    (next_u32() >> 8) as f64 / (1u32 << 24) as f64
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_seed_repeats() {
+        seed(42);
+        let first: Vec<u32> = (0..8).map(|_| next_u32()).collect();
+        seed(42);
+        let second: Vec<u32> = (0..8).map(|_| next_u32()).collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_range_and_mean() {
+        seed(7);
+        let count = 10000;
+        let sum = (0..count).fold(0.0, |sum, _| {
+            let x = random();
+            assert!((0.0..1.0).contains(&x));
+            assert!((x as f32) < 1.0);
+            sum + x
+        });
+        assert!((sum / count as f64 - 0.5).abs() < 0.01);
+    }
+}
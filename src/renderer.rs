@@ -1,93 +1,151 @@
 
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::adaptive::{AdaptiveRender, AdaptiveSampling, PixelStats};
+use crate::aov::Aov;
+use crate::film::{Film, PixelFilter};
+use crate::integrator::{self, IntegratorSettings};
 use crate::primitives::{
+    Float,
     Vec2i,
     Vec2f,
     Vec3,
-    Ray,
     Rect,
 };
-use crate::scene::{
-    Hittable,
-    Scene,
-};
+use crate::scene::Scene;
+use crate::sampler::{self, Sampler};
+use crate::spectrum::{self, ColorMode};
+use crate::stats::{self as render_stats, RenderStats};
 
 
 use itertools::{self, Itertools};
 
-const SKY_COLOR: Vec3 = Vec3 { x: 0.5, y: 0.7, z: 1.0};
-
+#[derive(Clone)]
 pub struct RenderProperties {
     pub samples: u32, // samples are averaged results over a pixel
     pub bounces: u32, // bounces are how far the ray will travel (in hits not total distance)
+    pub color_mode: ColorMode,
+    pub aovs: Vec<Aov>, // extra passes to collect besides the color
+    pub adaptive: Option<AdaptiveSampling>, // `samples` becomes the average when set
+    pub sampler: Sampler,
+    pub filter: PixelFilter, // how the samples are spread over the pixels
+    pub roulette_depth: Option<u32>, // bounces before Russian roulette may end a path
+    pub integrator: IntegratorSettings,
 }
 
-fn to_uv(coord: Vec2i, img_size: Vec2i) -> Vec2f {
-    let u = (coord.x as f32) / ((img_size.x - 1) as f32);
-    let v = (coord.y as f32) / ((img_size.y - 1) as f32);
-    Vec2f::new(u, v)
+// What the command line and the web page start with
+impl Default for RenderProperties {
+    fn default() -> Self {
+        Self {
+            samples: 10,
+            bounces: 50,
+            color_mode: ColorMode::Rgb,
+            aovs: Vec::new(),
+            adaptive: None,
+            sampler: Sampler::Sobol,
+            filter: PixelFilter::default(),
+            roulette_depth: Some(3),
+            integrator: IntegratorSettings::default(),
+        }
+    }
 }
 
-fn ray_color(
-    r: Ray, surface: &Hittable, depth: u32,
-) -> Vec3 {
-    // recursion guard
-    if depth == 0 {
-        return Vec3::zero();
-    }
-    
-    // cast a ray, interrogate hit record
-    if let Some(record) = surface.hit(r, 0.001, f32::INFINITY){
-        let mut scattered = Ray {
-            orig: Vec3::zero(),
-            dir: Vec3::zero(),
-        };
-        let mut attenuation = Vec3::zero();
-        if record.material.scatter(
-            r,
-            &record,
-            &mut attenuation,
-            &mut scattered,
-        ) {
-            return attenuation * ray_color(
-                scattered, surface, depth-1,
-            );
-        }
-    } // TODO: explicit else block
-    // Rust gets angry about the inner if{} block because it evaluates to ()
-    // when the else path is taken. This is a problem for a function
-    // that returns Vec3 and not ().
+// `jitter` is the position inside the pixel, in 0..1. The image covers 0..1 edge to edge.
+pub(crate) fn to_uv(coord: Vec2i, jitter: Vec2f, img_size: Vec2i) -> Vec2f {
+    let u = (coord.x as Float + jitter.x) / img_size.x as Float;
+    let v = (coord.y as Float + jitter.y) / img_size.y as Float;
+    Vec2f::new(u, v)
+}
 
-    { // when nothing is struck, return sky color
-        let unitdir = Vec3::as_unit(r.dir);
-        let t = 0.5 * (unitdir.y + 1.0);
-        return Vec3::ones() * (1.0 - t) + SKY_COLOR * t
-    }
+// Results of all the samples taken for one pixel
+pub(crate) struct PixelSample {
+    pub stats: PixelStats,
+    pub aovs: Vec<Vec3>, // same order as `RenderProperties::aovs`, averaged (the depth is the nearest)
 }
 
-fn sample_pixel(
+pub(crate) fn sample_pixel(
     coord: Vec2i, // location in image/screen space
     scene: &Scene,  // scene we're drawing
     render_props: &RenderProperties,
     img_size: Vec2i,
+    samples: Range<u32>, // indices into the pixel's sample sequence
+    film: &mut Film, // where the color samples go
     // Supplied by the execution environment (the thread)
-) -> Vec3{
-    (0..render_props.samples)
-    .fold(
-        Vec3::zero(),
-        |color, _sample| -> Vec3 {
-            let uv = to_uv(coord, img_size);
-            let ray = scene.camera.get_ray(uv.x, uv.y);
-            if ray.dir.x.is_nan() {
-                panic!("Ray dir.x is NAN");
+) -> PixelSample {
+    let first_sample = samples.start;
+    let count = samples.len() as u32;
+    let scale = 1.0 / count as Float;
+    let mut stats = PixelStats::default();
+    let mut aovs: Vec<Vec3> = render_props.aovs.iter()
+        .map(|aov| if *aov == Aov::Depth { Vec3::ones() * Float::INFINITY } else { Vec3::zero() })
+        .collect();
+    let integrator = render_props.integrator.build();
+    sampler::start_pixel(render_props.sampler, coord, samples.clone());
+    for sample in samples {
+        sampler::start_sample(sample);
+        let jitter = sampler::next_2d();
+        let uv = to_uv(coord, jitter, img_size);
+        let ray = scene.camera.get_ray(uv.x, uv.y);
+        if ray.dir.x.is_nan() {
+            panic!("Ray dir.x is NAN");
+        }
+        render_stats::count_primary_ray();
+        let rays_before = render_stats::rays();
+        // shared by the integrator and the AOVs
+        let hit = integrator::first_hit(ray, scene);
+        let color = match render_props.color_mode {
+            ColorMode::Rgb => integrator.radiance(ray, hit, scene, render_props),
+            ColorMode::Spectral { wavelengths } => {
+                // accumulate the wavelengths in XYZ, then convert the sample to RGB
+                let xyz = spectrum::sample_wavelengths(wavelengths)
+                .fold(Vec3::zero(), |xyz, lambda| {
+                    let radiance = integrator.spectral_radiance(ray, hit, scene, render_props, lambda);
+                    xyz + spectrum::radiance_to_xyz(lambda, radiance) / wavelengths as Float
+                });
+                spectrum::xyz_to_balanced_rgb(xyz)
+            }
+        };
+        render_stats::count_path_rays(render_stats::rays() - rays_before);
+        stats.add(color);
+        let position = Vec2f::new(coord.x as Float + jitter.x, coord.y as Float + jitter.y);
+        film.add_sample(&render_props.filter, position, color);
+
+        if !render_props.aovs.is_empty() {
+            for (value, aov) in aovs.iter_mut().zip(render_props.aovs.iter()) {
+                if !aov.is_first_hit() {
+                    continue;
+                } else if *aov == Aov::Depth {
+                    // the nearest, so a sample missing doesn't push a silhouette to infinity
+                    let depth = aov.first_hit_value(scene, hit.as_ref());
+                    *value = Vec3::ones() * value.x.min(depth.x);
+                } else if !aov.is_id() {
+                    *value += aov.first_hit_value(scene, hit.as_ref()) * scale;
+                } else if sample == first_sample {
+                    *value = aov.first_hit_value(scene, hit.as_ref());
+                }
             }
-            color + ray_color(ray, &scene.world, render_props.bounces)
         }
-    )
+    }
+    for (value, aov) in aovs.iter_mut().zip(render_props.aovs.iter()) {
+        if *aov == Aov::SampleCount {
+            *value = Vec3::ones() * count as Float;
+        }
+    }
+    PixelSample {
+        stats,
+        aovs,
+    }
 }
 
+// One buffer per render pass, row-major
+pub type AovBuffers = HashMap<Aov, Vec<Vec3>>;
+
 pub struct Tile {
     _bounds: Rect,
-    pub pixels: Vec<Vec3>,
+    pub film: Film, // the color, reaches a bit past the bounds with wide filters
+    pub buffers: AovBuffers, // whatever extra passes were requested
+    pub stats: Vec<PixelStats>,
 }
 
 impl Tile {
@@ -96,35 +154,330 @@ impl Tile {
         img_size: Vec2i,    // final image resolution (needed for proper UV mapping)
         scene: &Scene,
         properties: &RenderProperties, // TODO: Place image size in render properties?
+        first_sample: u32, // where in the pixels' sample sequences to start, for progressive rendering
     ) -> Self {
         let pixel_iter = (bounds.y..(bounds.y + bounds.h))
             .cartesian_product( bounds.x..(bounds.x + bounds.w));
-        let pixels = pixel_iter.map(
-            |coord| -> Vec3 {
+        let mut film = Film::for_tile(bounds, img_size, &properties.filter);
+        let samples: Vec<PixelSample> = pixel_iter.map(
+            |coord| -> PixelSample {
                 sample_pixel(
                     Vec2i{x: coord.1, y: coord.0},
                     scene,
                     properties,
                     img_size,
+                    first_sample..first_sample + properties.samples,
+                    &mut film,
                 )
             }
         ).collect();
+
+        let mut buffers = AovBuffers::new();
+        for (idx, aov) in properties.aovs.iter().enumerate() {
+            buffers.insert(*aov, samples.iter().map(|px| px.aovs[idx]).collect());
+        }
         Self {
             _bounds: bounds,
-            pixels
+            film,
+            buffers,
+            stats: samples.iter().map(|px| px.stats).collect(),
         }
     }
+
+    pub fn buffer(&self, aov: Aov) -> Option<&[Vec3]> {
+        self.buffers.get(&aov).map(|buffer| buffer.as_slice())
+    }
+
     pub fn render_line(
         y: i32, // bounding rect and line
         img_size: Vec2i,
         scene: &Scene,
         properties: &RenderProperties,
+        first_sample: u32,
     ) -> Self {
         Tile::render_tile(
             Rect{ x: 0, y, w: img_size.x, h: 1 },
             img_size,
             scene,
             properties,
+            first_sample,
         )
     }
 }
+
+/*
+ * Renders the whole image, top row first.
+ *
+ * The color pass is the filtered linear radiance of every pixel, ready for
+ * the output stage or one of the HDR writers.
+ */
+pub fn render_image(
+    img_size: Vec2i,
+    scene: &Scene,
+    properties: &RenderProperties,
+) -> AovBuffers {
+    return render_image_with_stats(img_size, scene, properties).0;
+}
+
+// `render_image()`, and what it took
+pub fn render_image_with_stats(
+    img_size: Vec2i,
+    scene: &Scene,
+    properties: &RenderProperties,
+) -> (AovBuffers, RenderStats) {
+    if let Some(adaptive) = &properties.adaptive {
+        let mut render = AdaptiveRender::new(img_size, properties, adaptive);
+        while render.render_next(scene) {}
+        return render.finish();
+    }
+    return render_pass_with_stats(img_size, scene, properties, 0);
+}
+
+/*
+ * Renders `properties.samples` samples of every pixel, starting at
+ * `first_sample` in their sequences. Passes with different starts can be
+ * averaged together with `accumulate()`. Always uniform, adaptive sampling
+ * is ignored.
+ */
+pub fn render_pass(
+    img_size: Vec2i,
+    scene: &Scene,
+    properties: &RenderProperties,
+    first_sample: u32,
+) -> AovBuffers {
+    return render_pass_with_stats(img_size, scene, properties, first_sample).0;
+}
+
+pub fn render_pass_with_stats(
+    img_size: Vec2i,
+    scene: &Scene,
+    properties: &RenderProperties,
+    first_sample: u32,
+) -> (AovBuffers, RenderStats) {
+    let mut render = TiledRender::new(img_size, properties, first_sample);
+    while render.render_next(scene) {}
+    return render.finish();
+}
+
+/*
+ * `render_pass()` one tile at a time, for callers that need to get a word
+ * in between (like the async renderer handing control back to the browser).
+ */
+pub struct TiledRender {
+    img_size: Vec2i,
+    properties: RenderProperties,
+    first_sample: u32,
+    rows_left: i32, // rows go top to bottom, like the buffers
+    film: Film,
+    buffers: AovBuffers,
+    stats: RenderStats,
+}
+
+impl TiledRender {
+    pub fn new(img_size: Vec2i, properties: &RenderProperties, first_sample: u32) -> TiledRender {
+        TiledRender {
+            img_size,
+            properties: properties.clone(),
+            first_sample,
+            rows_left: img_size.y,
+            film: Film::new(Rect { x: 0, y: 0, w: img_size.x, h: img_size.y }),
+            buffers: AovBuffers::new(),
+            stats: RenderStats::new(img_size, properties.samples),
+        }
+    }
+
+    // Renders the next tile, false once there's none left
+    pub fn render_next(&mut self, scene: &Scene) -> bool {
+        if self.rows_left == 0 {
+            return false;
+        }
+        self.rows_left -= 1;
+        let (tile, milliseconds) = self.stats.measure(|| {
+            Tile::render_line(self.rows_left, self.img_size, scene, &self.properties, self.first_sample)
+        });
+        self.stats.tile_milliseconds.push(milliseconds);
+        self.film.merge(&tile.film);
+        for (aov, pixels) in tile.buffers {
+            self.buffers.entry(aov).or_default().extend(pixels);
+        }
+        return true;
+    }
+
+    pub fn tiles_done(&self) -> u32 {
+        (self.img_size.y - self.rows_left) as u32
+    }
+
+    // What the tiles so far took
+    pub fn stats(&self) -> &RenderStats {
+        &self.stats
+    }
+
+    // The color so far, black where nothing has been rendered yet
+    pub fn color(&self) -> Vec<Vec3> {
+        self.film.resolve()
+    }
+
+    pub fn finish(mut self) -> (AovBuffers, RenderStats) {
+        self.buffers.insert(Aov::Color, self.film.resolve());
+        return (self.buffers, self.stats);
+    }
+}
+
+/*
+ * Averages `pass` into `total`, which already holds `passes` passes of the
+ * same number of samples. The sample counts add up, the depth is the
+ * nearest of them (misses are infinite, which doesn't average) and the IDs
+ * stay those of the first pass.
+ */
+pub fn accumulate(total: &mut AovBuffers, pass: AovBuffers, passes: u32) {
+    if passes == 0 {
+        *total = pass;
+        return;
+    }
+    let weight = 1.0 / (passes + 1) as Float;
+    for (aov, pixels) in pass {
+        let Some(sums) = total.get_mut(&aov) else {
+            continue;
+        };
+        if aov.is_id() {
+            continue;
+        }
+        for (sum, px) in sums.iter_mut().zip(pixels) {
+            *sum = match aov {
+                Aov::SampleCount => *sum + px,
+                Aov::Depth => Vec3::ones() * sum.x.min(px.x),
+                _ => *sum + (px - *sum) * weight,
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random;
+
+    fn mean_luminance(pixels: &[Vec3]) -> Float {
+        let sum = pixels.iter().fold(Vec3::zero(), |acc, px| acc + *px) / pixels.len() as Float;
+        0.2126 * sum.x + 0.7152 * sum.y + 0.0722 * sum.z
+    }
+
+    #[test]
+    fn test_uv_covers_the_image() {
+        let img_size = Vec2i::new(4, 3);
+        assert_eq!(to_uv(Vec2i::new(0, 0), Vec2f::zero(), img_size), Vec2f::zero());
+        assert_eq!(to_uv(Vec2i::new(3, 2), Vec2f::ones(), img_size), Vec2f::ones());
+        // pixel centers are symmetric around the middle
+        let first = to_uv(Vec2i::new(0, 0), Vec2f::new(0.5, 0.5), img_size);
+        let last = to_uv(Vec2i::new(3, 2), Vec2f::new(0.5, 0.5), img_size);
+        assert!((first.x + last.x - 1.0).abs() < 1e-6 && (first.y + last.y - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_roulette_keeps_brightness() {
+        let img_size = Vec2i::new(24, 16);
+        random::seed(3);
+        let scene = Scene::random_scene(1.5);
+        let render = |roulette_depth| {
+            let properties = RenderProperties {
+                samples: 48,
+                bounces: 200,
+                roulette_depth,
+                ..Default::default()
+            };
+            render_image(img_size, &scene, &properties)[&Aov::Color].clone()
+        };
+        let reference = mean_luminance(&render(None));
+        let roulette = mean_luminance(&render(Some(2)));
+        assert!((roulette / reference - 1.0).abs() < 0.02, "{} vs {}", roulette, reference);
+    }
+
+    #[test]
+    fn test_tiled_render_goes_top_down() {
+        let img_size = Vec2i::new(6, 4);
+        random::seed(7);
+        let scene = Scene::random_scene(1.5);
+        let properties = RenderProperties {
+            samples: 2,
+            bounces: 4,
+            aovs: vec![Aov::Depth],
+            roulette_depth: None,
+            ..Default::default()
+        };
+        let mut render = TiledRender::new(img_size, &properties, 0);
+        assert!(render.render_next(&scene));
+        // only the top row so far
+        let color = render.color();
+        assert!(color[..6].iter().all(|px| *px != Vec3::zero()));
+        assert!(color[6..].iter().all(|px| *px == Vec3::zero()));
+        while render.render_next(&scene) {}
+        assert_eq!(render.tiles_done(), 4);
+        let (buffers, stats) = render.finish();
+        assert_eq!(stats.tiles(), 4);
+        assert_eq!(buffers[&Aov::Depth].len(), 24);
+        assert_eq!(buffers[&Aov::Color], render_image(img_size, &scene, &properties)[&Aov::Color]);
+    }
+
+    #[test]
+    fn test_accumulated_passes_match_one_render() {
+        let img_size = Vec2i::new(12, 8);
+        random::seed(5);
+        let scene = Scene::random_scene(1.5);
+        let properties = RenderProperties {
+            samples: 4,
+            bounces: 8,
+            aovs: vec![Aov::Normal, Aov::SampleCount],
+            roulette_depth: None,
+            ..Default::default()
+        };
+        let whole = render_image(img_size, &scene, &properties);
+        let single = RenderProperties { samples: 1, ..properties.clone() };
+        let mut total = AovBuffers::new();
+        for pass in 0..4 {
+            accumulate(&mut total, render_pass(img_size, &scene, &single, pass), pass);
+        }
+        // same samples, so only the order of the sums differs
+        for (a, b) in whole[&Aov::Color].iter().zip(total[&Aov::Color].iter()) {
+            assert!((*a - *b).near_zero(), "{:?} vs {:?}", a, b);
+        }
+        assert_eq!(total[&Aov::SampleCount][0], Vec3::ones() * 4.0);
+    }
+
+    #[test]
+    fn test_accumulated_misses_stay_infinite() {
+        let miss = Vec3::ones() * Float::INFINITY;
+        let pass = |depth: Vec3| AovBuffers::from([(Aov::Depth, vec![miss, depth])]);
+        let mut total = AovBuffers::new();
+        accumulate(&mut total, pass(miss), 0);
+        accumulate(&mut total, pass(Vec3::ones() * 3.0), 1);
+        accumulate(&mut total, pass(Vec3::ones() * 2.0), 2);
+        assert_eq!(total[&Aov::Depth], vec![miss, Vec3::ones() * 2.0]);
+    }
+
+    #[test]
+    fn test_depth_is_the_nearest_hit() {
+        let img_size = Vec2i::new(12, 8);
+        random::seed(5);
+        let scene = Scene::random_scene(1.5);
+        let properties = RenderProperties {
+            samples: 4,
+            bounces: 2,
+            aovs: vec![Aov::Depth],
+            roulette_depth: None,
+            ..Default::default()
+        };
+        let whole = render_image(img_size, &scene, &properties);
+        let single = RenderProperties { samples: 1, ..properties.clone() };
+        let passes: Vec<AovBuffers> = (0..4).map(|pass| render_pass(img_size, &scene, &single, pass)).collect();
+        let mut silhouettes = 0;
+        for (idx, depth) in whole[&Aov::Depth].iter().enumerate() {
+            let depths: Vec<Float> = passes.iter().map(|pass| pass[&Aov::Depth][idx].x).collect();
+            assert_eq!(depth.x, depths.iter().copied().fold(Float::INFINITY, Float::min));
+            if depth.x.is_finite() && depths.iter().any(|depth| depth.is_infinite()) {
+                silhouettes += 1;
+            }
+        }
+        // pixels with samples on the horizon and in the sky
+        assert!(silhouettes > 0);
+    }
+}
@@ -0,0 +1,398 @@
+use std::cell::Cell;
+use std::ops::Range;
+
+use wasm_bindgen::prelude::*;
+
+use crate::blue_noise;
+use crate::primitives::{Float, Vec2f, Vec2i, Vec3, consts};
+use crate::random::random;
+
+/*
+ * Sample sequences for the camera rays.
+ *
+ * Every random decision of a camera sample (pixel position, lens position,
+ * then the BSDF at every bounce) takes the next "dimension" of the current
+ * pixel's sequence. With anything but `Random` the samples of a pixel cover
+ * each dimension evenly instead of clumping, which converges faster.
+ *
+ * Like the random number generator the state is per thread: `start_pixel()`
+ * and `start_sample()` are called by the renderer, the rest of the tracer
+ * just asks for `next_1d()` / `next_2d()`.
+ */
+
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sampler {
+    Random,     // white noise, the old behavior
+    Stratified, // jittered strata, shuffled per dimension (correlated multi-jittered in 2D)
+    Halton,     // radical inverses, rotated per pixel
+    Sobol,      // Owen scrambled, see "Practical Hash-based Owen Scrambling" (Burley 2020)
+    BlueNoise,  // Sobol rotated by a blue noise mask, so the error looks like blue noise
+}
+
+impl Sampler {
+    pub const ALL: [Sampler; 5] = [
+        Sampler::Random,
+        Sampler::Stratified,
+        Sampler::Halton,
+        Sampler::Sobol,
+        Sampler::BlueNoise,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Sampler::Random => "random",
+            Sampler::Stratified => "stratified",
+            Sampler::Halton => "halton",
+            Sampler::Sobol => "sobol",
+            Sampler::BlueNoise => "blue_noise",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Sampler> {
+        Sampler::ALL.iter().find(|sampler| sampler.name() == name).copied()
+    }
+}
+
+#[derive(Copy, Clone)]
+struct State {
+    sampler: Sampler,
+    pixel: Vec2i,
+    seed: u32,          // per pixel
+    first: u32,         // the batch of samples being taken
+    count: u32,
+    index: u32,         // current sample
+    dimension: u32,
+}
+
+thread_local! {
+    static STATE: Cell<State> = const { Cell::new(State {
+        sampler: Sampler::Random,
+        pixel: Vec2i { x: 0, y: 0 },
+        seed: 0,
+        first: 0,
+        count: 1,
+        index: 0,
+        dimension: 0,
+    }) };
+}
+
+/*
+ * Starts taking `samples` for a pixel. The range continues the pixel's
+ * sequence when more samples get added later (adaptive sampling).
+ */
+pub fn start_pixel(sampler: Sampler, pixel: Vec2i, samples: Range<u32>) {
+    let seed = hash(pixel.x as u32 ^ hash(pixel.y as u32));
+    STATE.with(|state| state.set(State {
+        sampler,
+        pixel,
+        seed,
+        first: samples.start,
+        count: samples.len() as u32,
+        index: samples.start,
+        dimension: 0,
+    }));
+}
+
+pub fn start_sample(index: u32) {
+    STATE.with(|state| {
+        let mut current = state.get();
+        current.index = index;
+        current.dimension = 0;
+        state.set(current);
+    });
+}
+
+// Takes the next dimension of the current sample
+pub fn next_1d() -> Float {
+    return next_2d().x;
+}
+
+// Takes the next two dimensions of the current sample, they are stratified together
+pub fn next_2d() -> Vec2f {
+    let state = STATE.with(|state| {
+        let current = state.get();
+        state.set(State { dimension: current.dimension + 1, ..current });
+        current
+    });
+    let dim_seed = hash(state.seed ^ hash(state.dimension));
+    let point = match state.sampler {
+        Sampler::Random => Vec2f::new(random() as Float, random() as Float),
+        Sampler::Stratified => {
+            let batch_seed = hash(dim_seed ^ state.first);
+            correlated_multi_jitter(state.index - state.first, state.count, batch_seed)
+        },
+        Sampler::Halton => {
+            let Some(bases) = HALTON_BASES.get(2 * state.dimension as usize..2 * state.dimension as usize + 2) else {
+                // ran out of primes, the deep bounces barely matter anyway
+                return Vec2f::new(random() as Float, random() as Float);
+            };
+            let rotation = Vec2f::new(to_unit(hash(dim_seed)), to_unit(hash(dim_seed ^ 1)));
+            Vec2f::new(
+                fract(radical_inverse(state.index, bases[0]) + rotation.x),
+                fract(radical_inverse(state.index, bases[1]) + rotation.y),
+            )
+        },
+        Sampler::Sobol => sobol_2d(state.index, dim_seed),
+        Sampler::BlueNoise => {
+            // same sequence for every pixel, only the rotation differs
+            let point = sobol_2d(state.index, hash(state.dimension));
+            let rotation = blue_noise_offset(state.pixel, state.dimension);
+            Vec2f::new(fract(point.x + rotation.x), fract(point.y + rotation.y))
+        },
+    };
+    return point;
+}
+
+// Uniform point on the unit disk (z = 0), Shirley's concentric mapping
+pub fn to_unit_disk(u: Vec2f) -> Vec3 {
+    let a = 2.0 * u.x - 1.0;
+    let b = 2.0 * u.y - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return Vec3::zero();
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, consts::FRAC_PI_4 * (b / a))
+    } else {
+        (b, consts::FRAC_PI_2 - consts::FRAC_PI_4 * (a / b))
+    };
+    Vec3::new(r * theta.cos(), r * theta.sin(), 0.0)
+}
+
+// Uniform direction
+pub fn to_unit_vector(u: Vec2f) -> Vec3 {
+    let z = 1.0 - 2.0 * u.x;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    let phi = 2.0 * consts::PI * u.y;
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+// Uniform point inside the unit ball, the radius takes a third dimension
+pub fn to_unit_ball(u: Vec2f, radius: Float) -> Vec3 {
+    to_unit_vector(u) * radius.cbrt()
+}
+
+fn fract(x: Float) -> Float {
+    // keeps the result below 1.0 after rounding
+    (x - x.floor()).min(1.0 - Float::EPSILON / 2.0)
+}
+
+// 24 bits, so the result stays below 1.0
+fn to_unit(bits: u32) -> Float {
+    (bits >> 8) as Float / (1u32 << 24) as Float
+}
+
+// lowbias32
+fn hash(mut x: u32) -> u32 {
+    x ^= x >> 16;
+    x = x.wrapping_mul(0x7feb352d);
+    x ^= x >> 15;
+    x = x.wrapping_mul(0x846ca68b);
+    x ^= x >> 16;
+    x
+}
+
+/*
+ * Random permutation of 0..len picked by `seed`, without storing it.
+ * From "Correlated Multi-Jittered Sampling" (Kensler 2013).
+ */
+fn permute(mut i: u32, len: u32, seed: u32) -> u32 {
+    let mut w = len - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < len {
+            break;
+        }
+    }
+    (i.wrapping_add(seed)) % len
+}
+
+/*
+ * Sample `index` of `count`: stratified on a m x n grid and on both axes
+ * (one sample per row and column of the fine grid), with random jitter.
+ */
+fn correlated_multi_jitter(index: u32, count: u32, seed: u32) -> Vec2f {
+    let m = (count as Float).sqrt() as u32;
+    let n = count.div_ceil(m);
+    let s = permute(index, count, seed.wrapping_mul(0x51633e2d));
+    let sx = permute(s % m, m, seed.wrapping_mul(0x68bc21eb));
+    let sy = permute(s / m, n, seed.wrapping_mul(0x02e5be93));
+    let jx = random() as Float;
+    let jy = random() as Float;
+    Vec2f::new(
+        fract(((s % m) as Float + (sy as Float + jx) / n as Float) / m as Float),
+        fract(((s / m) as Float + (sx as Float + jy) / m as Float) / n as Float),
+    )
+}
+
+// pairs of primes, one pair per dimension
+const HALTON_BASES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53,
+    59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107, 109, 113, 127, 131,
+];
+
+fn radical_inverse(mut index: u32, base: u32) -> Float {
+    let inv_base = 1.0 / base as f64;
+    let mut reversed = 0.0;
+    let mut scale = inv_base;
+    while index > 0 {
+        reversed += (index % base) as f64 * scale;
+        index /= base;
+        scale *= inv_base;
+    }
+    reversed as Float
+}
+
+fn sobol_x(index: u32) -> u32 {
+    index.reverse_bits()
+}
+
+fn sobol_y(mut index: u32) -> u32 {
+    // direction numbers of the second dimension: v = v ^ (v >> 1)
+    let mut v = 1u32 << 31;
+    let mut result = 0;
+    while index != 0 {
+        if index & 1 != 0 {
+            result ^= v;
+        }
+        index >>= 1;
+        v ^= v >> 1;
+    }
+    result
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x
+}
+
+fn owen_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+// First two Sobol dimensions, with a shuffled index and Owen scrambled values
+fn sobol_2d(index: u32, seed: u32) -> Vec2f {
+    let index = owen_scramble(index, seed);
+    Vec2f::new(
+        to_unit(owen_scramble(sobol_x(index), hash(seed ^ 0xa511e9b3))),
+        to_unit(owen_scramble(sobol_y(index), hash(seed ^ 0x63d83595))),
+    )
+}
+
+// Two mask values for the pixel, moved around for every dimension
+fn blue_noise_offset(pixel: Vec2i, dimension: u32) -> Vec2f {
+    let size = blue_noise::SIZE as u32;
+    let lookup = |shift: u32| {
+        let shift = hash(shift);
+        let x = (pixel.x as u32).wrapping_add(shift) % size;
+        let y = (pixel.y as u32).wrapping_add(shift >> 16) % size;
+        (blue_noise::MASK[(y * size + x) as usize] as Float + 0.5) / blue_noise::MASK.len() as Float
+    };
+    Vec2f::new(lookup(2 * dimension), lookup(2 * dimension + 1))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // every cell of a `cells` x `cells` grid holds exactly one of the points
+    fn is_stratified(points: &[Vec2f], cells: usize) -> bool {
+        let mut hits = vec![0; cells * cells];
+        for p in points {
+            hits[(p.y * cells as Float) as usize * cells + (p.x * cells as Float) as usize] += 1;
+        }
+        hits.iter().all(|count| *count == 1)
+    }
+
+    #[test]
+    fn test_sobol_is_stratified() {
+        // the (0, 2)-sequence property survives Owen scrambling
+        for seed in [0, 1, 0xdeadbeef] {
+            let points: Vec<Vec2f> = (0..16).map(|i| sobol_2d(i, seed)).collect();
+            assert!(is_stratified(&points, 4));
+        }
+    }
+
+    #[test]
+    fn test_multi_jitter_is_stratified() {
+        let points: Vec<Vec2f> = (0..16).map(|i| correlated_multi_jitter(i, 16, 7)).collect();
+        assert!(is_stratified(&points, 4));
+        // and one sample per column of the fine grid
+        let mut columns: Vec<usize> = points.iter().map(|p| (p.x * 16.0) as usize).collect();
+        columns.sort();
+        assert_eq!(columns, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(1, 2), 0.5);
+        assert_eq!(radical_inverse(3, 2), 0.75);
+        assert!((radical_inverse(5, 3) - 7.0 / 9.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_blue_noise_mask() {
+        let mask = blue_noise::MASK;
+        let mut ranks: Vec<usize> = mask.iter().map(|rank| *rank as usize).collect();
+        ranks.sort();
+        assert_eq!(ranks, (0..mask.len()).collect::<Vec<_>>());
+        // the darkest 10% are spread out: no two of them are neighbors
+        let size = blue_noise::SIZE;
+        let dark = |x: usize, y: usize| (mask[(y % size) * size + x % size] as usize) < mask.len() / 10;
+        for y in 0..size {
+            for x in 0..size {
+                assert!(!(dark(x, y) && (dark(x + 1, y) || dark(x, y + 1))));
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_sampler_in_range() {
+        for sampler in Sampler::ALL {
+            start_pixel(sampler, Vec2i::new(3, 5), 0..8);
+            for index in 0..8 {
+                start_sample(index);
+                for _ in 0..40 {
+                    let p = next_2d();
+                    assert!((0.0..1.0).contains(&p.x) && (0.0..1.0).contains(&p.y), "{:?}", sampler);
+                }
+            }
+            assert_eq!(Sampler::from_name(sampler.name()), Some(sampler));
+        }
+    }
+
+    #[test]
+    fn test_disk_mapping() {
+        for i in 0..64 {
+            let p = to_unit_disk(sobol_2d(i, 3));
+            assert!(p.length_squared() <= 1.0 + 1e-6);
+        }
+        assert!((to_unit_vector(Vec2f::new(0.3, 0.8)).length() - 1.0).abs() < 1e-5);
+    }
+}
@@ -1,77 +1,74 @@
 
-use web_sys::js_sys::Math::random;
+use std::cell::Cell;
 
-use crate::primitives::{Ray, Vec2f, Vec3};
+use wasm_bindgen::prelude::*;
 
+use crate::materials::MaterialLibrary;
+use crate::orbit::OrbitCamera;
+use crate::random::random;
+
+use crate::primitives::{Float, Ray, Vec2f, Vec3, consts, offset_ray_origin};
+use crate::sampler;
+use crate::spectrum;
+use crate::spheres::{Sphere, Spheres};
+
+#[derive(Copy, Clone)]
 pub struct HitRecord{
     pub p: Vec3,
+    pub p_error: Vec3, // bound on how far `p` is from the true hit, per axis
     pub normal: Vec3,
-    pub material: Material,
-    pub t: f32,
+    pub material: u32, // index into `Scene::materials`
+    pub t: Float,
     pub front_face: bool,
+    pub object_index: u32, // index of the object in the top level list, see `Scene::object_id()`
 }
 
 impl HitRecord{
-    pub fn set_face_normal(&mut self, r: Ray, outward_normal: Vec3) -> (){
+    pub fn set_face_normal(&mut self, r: Ray, outward_normal: Vec3) {
         self.front_face = Vec3::dot(r.dir, outward_normal) < 0.0;
         self.normal = if self.front_face { outward_normal } else { -outward_normal };
     }
+
+    /*
+     * A ray leaving the hit in direction `dir`, starting just far enough off
+     * the surface that it can't hit it again right away. Its hits can then
+     * be anywhere in front of it, instead of past a fixed epsilon that's too
+     * big for tiny objects and too small for huge ones.
+     */
+    pub fn spawn_ray(&self, dir: Vec3) -> Ray {
+        Ray {
+            orig: offset_ray_origin(self.p, self.p_error, self.normal, dir),
+            dir,
+        }
+    }
+}
+
+thread_local! {
+    static INTERSECTION_TESTS: Cell<u64> = const { Cell::new(0) };
+}
+
+// Ray/object intersection tests done by the current thread so far
+pub fn intersection_tests() -> u64 {
+    INTERSECTION_TESTS.with(|tests| tests.get())
 }
 
+// Intersection tests done by the current thread so far
+pub(crate) fn count_intersection_tests(count: u64) {
+    INTERSECTION_TESTS.with(|tests| tests.set(tests.get() + count));
+}
+
+/*
+ * A description of the objects, for building scenes with. `Scene::new()`
+ * flattens it into the `Spheres` store that rays are actually traced
+ * against, nested lists included.
+ */
 #[derive (Clone)]
 pub enum Hittable {
-    Sphere { center: Vec3, radius: f32, material: Material },
+    Sphere { center: Vec3, radius: Float, material: Material },
     HittableList { hittables: Vec<Hittable> }
 }
 
 impl Hittable {
-    pub fn hit(&self, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
-        match self {
-            Hittable::HittableList { hittables } => {
-                hittables.iter()
-                .map( |obj| -> Option<HitRecord> {
-                    obj.hit(r, t_min, t_max)
-                }).filter(|obj| obj.is_some())
-                .min_by(|lhs, rhs| {
-                    let lhs = lhs.as_ref().unwrap();
-                    let rhs = rhs.as_ref().unwrap();
-                    lhs.t.partial_cmp(&rhs.t).expect("Couldn't compare??")
-                }).unwrap_or(None)
-            }
-
-            Hittable::Sphere { center, radius, material } => {
-                let oc = r.orig - *center;
-                let a = r.dir.length_squared();
-                let half_b = Vec3::dot(oc, r.dir);
-                let c = oc.length_squared() - radius * radius;
-                let discriminant = half_b*half_b - a*c;
-
-                if discriminant < 0.0 {
-                    return None;
-                }
-                let sqrtd = discriminant.sqrt();
-
-                // nearest root that lies within tolerance
-                let mut root = (-half_b - sqrtd) / a;
-                if root < t_min || root > t_max {
-                    root = (-half_b + sqrtd) / a;
-                    if root < t_min || root > t_max {
-                        return None;
-                    }
-                }
-                let mut record = HitRecord{
-                    p: r.at(root),
-                    normal: (r.at(root) - *center) / *radius,
-                    material: *material,
-                    t: root,
-                    front_face: false,
-                };
-                let outward_normal = (record.p - *center) / *radius;
-                record.set_face_normal(r, outward_normal);
-                Some(record)
-            }
-        }
-    }
     pub fn push(&mut self, item: Hittable) {
         if let Hittable::HittableList { hittables } = self {
             hittables.push(item);
@@ -80,11 +77,12 @@ impl Hittable {
 }
 
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Material{
     Lambertian { albedo: Vec3 },
-    Metal { albedo:Vec3, fuzz: f32 },
-    Dielectric { index_refraction: f32 },
+    Metal { albedo:Vec3, fuzz: Float },
+    // dispersion is only visible in spectral mode, see `spectrum::cauchy_ior`
+    Dielectric { index_refraction: Float, dispersion: Float },
 }
 
 impl Material {
@@ -97,7 +95,7 @@ impl Material {
     ) -> bool {
         match self {
             Material::Lambertian { albedo } => {
-                let scatter_dir = rec.normal + Vec3::rand_unit_vector();
+                let scatter_dir = rec.normal + sampler::to_unit_vector(sampler::next_2d());
                 // The compiler might be smart enough to compute this ^^^ just once. In which case,
                 // I don't need to do this weird dance. Oh well. It'll work.
                 let scatter_dir = if scatter_dir.near_zero() {  // if near zero,
@@ -109,10 +107,7 @@ impl Material {
                 //TODO: Revisit this out-parameter pattern
                 // It's a side effect of C++'s obtuse move semantics (and the RTIOW author not
                 // using them at all)
-                *scattered = Ray{
-                    orig: rec.p,
-                    dir: scatter_dir
-                };
+                *scattered = rec.spawn_ray(scatter_dir);
                 *attenuation = *albedo; // deref on both sides? Wacky
                 return true;
             },
@@ -121,38 +116,81 @@ impl Material {
                     Vec3::as_unit(ray_in.dir),
                     rec.normal
                 );
-                *scattered = Ray{
-                    orig: rec.p,
-                    dir: reflected + Vec3::rand_in_unit_sphere() * *fuzz,
-                };
+                *scattered = rec.spawn_ray(
+                    reflected + sampler::to_unit_ball(sampler::next_2d(), sampler::next_1d()) * *fuzz
+                );
                 *attenuation = *albedo;
                 return Vec3::dot(scattered.dir, rec.normal) > 0.0;
             },
-            Material::Dielectric { index_refraction } => {
+            Material::Dielectric { index_refraction, dispersion: _ } => {
                 *attenuation = Vec3::ones();
-                let refraction_ratio = if rec.front_face { 1.0 / index_refraction } else { *index_refraction };
-                
-                let unit_direction = Vec3::as_unit(ray_in.dir);
-                let cos_theta = Vec3::dot(-unit_direction, rec.normal).min(1.0);
-                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
-
-                let cannot_refract = refraction_ratio * sin_theta > 1.0;
-                let distrib_zero_one = Vec2f::new(0.0, 1.0);
-                let direction = if cannot_refract || Material::reflectance(cos_theta, refraction_ratio) > crate::lerp(distrib_zero_one, random() as f32) {
-                    Vec3::reflect(unit_direction, rec.normal)
-                } else {
-                    Vec3::refract(unit_direction, rec.normal, refraction_ratio)
-                };
-                *scattered = Ray {
-                    orig: rec.p,
-                    dir: direction 
-                };
+                *scattered = Material::refract_dielectric(ray_in, rec, *index_refraction);
                 return true;
             },
         }
     }
 
-    fn reflectance(cosine: f32, ref_idx: f32) -> f32 {
+    // Spectral version of `scatter()`. The attenuation is the reflectance at `lambda`.
+    pub fn scatter_spectral(
+        &self,
+        ray_in: Ray,
+        rec: &HitRecord,
+        lambda: Float,
+        attenuation: &mut Float,
+        scattered: &mut Ray,
+    ) -> bool {
+        match self {
+            Material::Dielectric { index_refraction, dispersion } => {
+                let ior = spectrum::cauchy_ior(*index_refraction, *dispersion, lambda);
+                *attenuation = 1.0;
+                *scattered = Material::refract_dielectric(ray_in, rec, ior);
+                return true;
+            },
+            _ => {
+                // everything else scatters the same for every wavelength, only
+                // the albedo needs upsampling
+                let mut rgb_attenuation = Vec3::zero();
+                let did_scatter = self.scatter(ray_in, rec, &mut rgb_attenuation, scattered);
+                *attenuation = spectrum::rgb_to_spectrum(rgb_attenuation, lambda);
+                return did_scatter;
+            },
+        }
+    }
+
+    fn refract_dielectric(ray_in: Ray, rec: &HitRecord, index_refraction: Float) -> Ray {
+        let refraction_ratio = if rec.front_face { 1.0 / index_refraction } else { index_refraction };
+
+        let unit_direction = Vec3::as_unit(ray_in.dir);
+        let cos_theta = Vec3::dot(-unit_direction, rec.normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+
+        let cannot_refract = refraction_ratio * sin_theta > 1.0;
+        let direction = if cannot_refract || Material::reflectance(cos_theta, refraction_ratio) > sampler::next_1d() {
+            Vec3::reflect(unit_direction, rec.normal)
+        } else {
+            Vec3::refract(unit_direction, rec.normal, refraction_ratio)
+        };
+        rec.spawn_ray(direction)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Material::Lambertian { .. } => "lambertian",
+            Material::Metal { .. } => "metal",
+            Material::Dielectric { .. } => "dielectric",
+        }
+    }
+
+    // Base color of the surface, for the albedo AOV
+    pub fn albedo(&self) -> Vec3 {
+        match self {
+            Material::Lambertian { albedo } => *albedo,
+            Material::Metal { albedo, .. } => *albedo,
+            Material::Dielectric { .. } => Vec3::ones(),
+        }
+    }
+
+    fn reflectance(cosine: Float, ref_idx: Float) -> Float {
         // Schlick's approximation for reflectance.
         let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
         let r0 = r0 * r0;
@@ -160,19 +198,85 @@ impl Material {
     }
 }
 
+/*
+ * A material, for passing to and from JS (which can't see `Material`
+ * itself). Made with one of the constructors below.
+ */
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug)]
+pub struct MaterialParams(Material);
+
+#[wasm_bindgen]
+impl MaterialParams {
+    pub fn lambertian(r: Float, g: Float, b: Float) -> MaterialParams {
+        MaterialParams(Material::Lambertian { albedo: Vec3::new(r, g, b) })
+    }
+
+    pub fn metal(r: Float, g: Float, b: Float, fuzz: Float) -> MaterialParams {
+        MaterialParams(Material::Metal { albedo: Vec3::new(r, g, b), fuzz: fuzz.clamp(0.0, 1.0) })
+    }
+
+    pub fn dielectric(index_refraction: Float, dispersion: Float) -> MaterialParams {
+        MaterialParams(Material::Dielectric { index_refraction, dispersion })
+    }
+}
+
+impl MaterialParams {
+    pub fn material(&self) -> Material {
+        self.0
+    }
+}
+
 // Camera
 
-pub fn degrees_to_radians(degrees: f32) -> f32 {
-    degrees * std::f32::consts::PI / 180.0
+pub fn degrees_to_radians(degrees: Float) -> Float {
+    degrees * consts::PI / 180.0
+}
+
+/*
+ * How the image maps to ray directions. Only the perspective camera has a
+ * lens (depth of field), the others are pinholes.
+ */
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CameraModel {
+    Perspective,     // thin lens, `vfov` across the image height
+    Orthographic,    // parallel rays, the view is the size of the perspective one at the focus distance
+    Equirectangular, // 360 x 180 degree panorama, centered on the view direction
+    Fisheye,         // equidistant, 180 degrees across the image height
+}
+
+impl CameraModel {
+    pub const ALL: [CameraModel; 4] = [
+        CameraModel::Perspective,
+        CameraModel::Orthographic,
+        CameraModel::Equirectangular,
+        CameraModel::Fisheye,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraModel::Perspective => "perspective",
+            CameraModel::Orthographic => "orthographic",
+            CameraModel::Equirectangular => "equirectangular",
+            CameraModel::Fisheye => "fisheye",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<CameraModel> {
+        CameraModel::ALL.iter().find(|model| model.name() == name).copied()
+    }
 }
 
+#[derive(Copy, Clone)]
 pub struct Camera {
+    pub model: CameraModel,
     origin: Vec3,
     lower_left_corner: Vec3,
     horizontal: Vec3,
     vertical: Vec3,
-    u: Vec3, v: Vec3, /*w: Vec3,*/
-    lens_radius: f32,
+    u: Vec3, v: Vec3, w: Vec3,
+    lens_radius: Float,
 }
 
 impl Camera {
@@ -180,10 +284,10 @@ impl Camera {
         lookfrom: Vec3,
         lookat: Vec3,
         vup: Vec3,
-        vfov: f32,
-        aspect_ratio: f32,
-        aperture: f32,
-        focus_dist: f32
+        vfov: Float,
+        aspect_ratio: Float,
+        aperture: Float,
+        focus_dist: Float
     ) -> Camera {
         let theta = degrees_to_radians(vfov);
         let h = (theta / 2.0).tan();
@@ -200,18 +304,76 @@ impl Camera {
         let lower_left_corner = orig - horiz / 2.0 - verti / 2.0 - w * focus_dist;
 
         Camera{
+            model: CameraModel::Perspective,
             origin: orig,
             lower_left_corner,
             horizontal: horiz,
             vertical: verti,
-            u, v, /* w,*/
+            u, v, w,
             lens_radius: aperture / 2.0,
         }
     }
 
-    pub fn get_ray(&self, s: f32, t: f32) -> Ray {
-        let rd = Vec3::rand_in_unit_disk() * self.lens_radius;
-        let offset = self.u * rd.x + self.v * rd.y;
+    // Distance from the camera to `p`, measured along the view direction
+    pub fn view_depth(&self, p: Vec3) -> Float {
+        Vec3::dot(p - self.origin, -self.w)
+    }
+
+    pub fn with_model(self, model: CameraModel) -> Camera {
+        Camera { model, ..self }
+    }
+
+    // Ray through the image position `(s, t)`, both in 0..1 from the lower left corner
+    pub fn get_ray(&self, s: Float, t: Float) -> Ray {
+        if self.model != CameraModel::Perspective {
+            return self.ray_through_lens(s, t, Vec3::zero()); // only the perspective camera has a lens
+        }
+        let lens = sampler::to_unit_disk(sampler::next_2d()) * self.lens_radius;
+        return self.ray_through_lens(s, t, lens);
+    }
+
+    // Like `get_ray()`, through the center of the lens. For picking what's under the pointer.
+    pub fn get_pinhole_ray(&self, s: Float, t: Float) -> Ray {
+        return self.ray_through_lens(s, t, Vec3::zero());
+    }
+
+    fn ray_through_lens(&self, s: Float, t: Float, lens: Vec3) -> Ray {
+        match self.model {
+            CameraModel::Perspective => self.perspective_ray(s, t, lens),
+            CameraModel::Orthographic => {
+                // from the plane through the camera, parallel to the focus plane
+                let target = self.lower_left_corner + self.horizontal * s + self.vertical * t;
+                Ray {
+                    orig: target + self.w * Vec3::dot(self.origin - target, self.w),
+                    dir: -self.w,
+                }
+            },
+            CameraModel::Equirectangular => {
+                let phi = (s - 0.5) * 2.0 * consts::PI; // longitude
+                let theta = (t - 0.5) * consts::PI;     // latitude
+                Ray {
+                    orig: self.origin,
+                    dir: (self.u * phi.sin() - self.w * phi.cos()) * theta.cos() + self.v * theta.sin(),
+                }
+            },
+            CameraModel::Fisheye => {
+                // the angle from the view direction grows linearly with the distance from the center
+                let aspect_ratio = self.horizontal.length() / self.vertical.length();
+                let x = (s - 0.5) * 2.0 * aspect_ratio;
+                let y = (t - 0.5) * 2.0;
+                let theta = (x * x + y * y).sqrt() * consts::FRAC_PI_2;
+                let phi = y.atan2(x);
+                Ray {
+                    orig: self.origin,
+                    dir: (self.u * phi.cos() + self.v * phi.sin()) * theta.sin() - self.w * theta.cos(),
+                }
+            },
+        }
+    }
+
+    // `lens` is the offset from the center of the lens
+    fn perspective_ray(&self, s: Float, t: Float, lens: Vec3) -> Ray {
+        let offset = self.u * lens.x + self.v * lens.y;
 
         let dir = self.lower_left_corner
                 + self.horizontal * s
@@ -225,12 +387,164 @@ impl Camera {
 }
 
 
+/*
+ * The camera and a list of objects. Besides its index in the list, every
+ * object has an ID that stays the same while others are added and removed,
+ * for editors to hold on to.
+ *
+ * Edits should go through the methods below, so the IDs stay in sync (and
+ * so there's one place to rebuild an acceleration structure, once there is one).
+ */
+#[derive(Clone)]
 pub struct Scene {
     pub camera: Camera,
-    pub world: Hittable,
+    pub world: Spheres,
+    pub materials: MaterialLibrary, // what the spheres and hits refer to by handle
+    object_ids: Vec<u32>, // same order as the objects
+    next_object_id: u32,
 }
 
 impl Scene {
+    // The objects of `world` in order, lists flattened
+    pub fn new(camera: Camera, world: Hittable) -> Scene {
+        let mut scene = Scene {
+            camera,
+            world: Spheres::default(),
+            materials: MaterialLibrary::default(),
+            object_ids: Vec::new(),
+            next_object_id: 0,
+        };
+        scene.add(world);
+        return scene;
+    }
+
+    fn add(&mut self, object: Hittable) {
+        match object {
+            Hittable::Sphere { center, radius, material } => {
+                self.add_sphere(center, radius, material);
+            },
+            Hittable::HittableList { hittables } => {
+                for object in hittables {
+                    self.add(object);
+                }
+            },
+        }
+    }
+
+    // The cover scene from the book, with the camera it was made for
+    pub fn random_scene(aspect_ratio: Float) -> Scene {
+        let mut scene = Scene::new(
+            Scene::random_scene_view().camera(aspect_ratio),
+            Scene::random_world(),
+        );
+        // names for the materials worth editing, the small spheres keep made up ones
+        let count = scene.object_count();
+        for (index, name) in [(0, "ground"), (count - 3, "glass"), (count - 2, "brown"), (count - 1, "mirror")] {
+            let handle = scene.world.get(index).expect("The cover scene has these").material;
+            scene.materials.rename(handle, name).expect("The names are unique");
+        }
+        return scene;
+    }
+
+    pub fn object_count(&self) -> usize {
+        self.world.len()
+    }
+
+    pub fn object_id(&self, index: usize) -> Option<u32> {
+        self.object_ids.get(index).copied()
+    }
+
+    pub fn object_index(&self, id: u32) -> Option<usize> {
+        self.object_ids.iter().position(|object_id| *object_id == id)
+    }
+
+    pub fn object(&self, id: u32) -> Option<Sphere> {
+        self.world.get(self.object_index(id)?)
+    }
+
+    // What a sphere or a hit refers to
+    pub fn material(&self, handle: u32) -> Material {
+        self.materials.material(handle)
+    }
+
+    /*
+     * Adds a sphere at the end of the list, returns its ID. The material
+     * is shared with anything that has exactly the same one.
+     */
+    pub fn add_sphere(&mut self, center: Vec3, radius: Float, material: Material) -> u32 {
+        let material = self.materials.find_or_add(material);
+        return self.add_sphere_with(center, radius, material)
+            .expect("The material was just found or added");
+    }
+
+    // With a material from the library, `None` if there's no such entry
+    pub fn add_sphere_with(&mut self, center: Vec3, radius: Float, material: u32) -> Option<u32> {
+        self.materials.get(material)?;
+        let id = self.next_object_id;
+        self.next_object_id += 1;
+        self.world.push(Sphere { center, radius, material });
+        self.object_ids.push(id);
+        return Some(id);
+    }
+
+    // The objects after it move down one index. False if there's no such object.
+    pub fn remove_object(&mut self, id: u32) -> bool {
+        let Some(index) = self.object_index(id) else {
+            return false;
+        };
+        self.world.remove(index);
+        self.object_ids.remove(index);
+        return true;
+    }
+
+    // False if there's no such object
+    pub fn set_sphere(&mut self, id: u32, center: Vec3, radius: Float) -> bool {
+        let Some(index) = self.object_index(id) else {
+            return false;
+        };
+        let Some(sphere) = self.world.get(index) else {
+            return false;
+        };
+        return self.world.set(index, Sphere { center, radius, ..sphere });
+    }
+
+    // Only this object changes, others sharing its material keep the old one
+    pub fn set_material(&mut self, id: u32, material: Material) -> bool {
+        let Some(index) = self.object_index(id) else {
+            return false;
+        };
+        let Some(sphere) = self.world.get(index) else {
+            return false;
+        };
+        let material = self.materials.find_or_add(material);
+        return self.world.set(index, Sphere { material, ..sphere });
+    }
+
+    // Points the object at a library entry. False if there's no such object or entry.
+    pub fn set_material_handle(&mut self, id: u32, material: u32) -> bool {
+        if self.materials.get(material).is_none() {
+            return false;
+        }
+        let Some(index) = self.object_index(id) else {
+            return false;
+        };
+        let Some(sphere) = self.world.get(index) else {
+            return false;
+        };
+        return self.world.set(index, Sphere { material, ..sphere });
+    }
+
+    // Where the cover scene is looked at from, as a starting point for the orbit controls
+    pub fn random_scene_view() -> OrbitCamera {
+        OrbitCamera::looking_at(
+            Vec3::new(13.0, 2.0, 3.0), // lookfrom
+            Vec3::zero(), // lookat
+            20.0, // vfov
+            0.1, // aperture
+            10.0, // dist_to_focus
+        )
+    }
+
     pub fn random_world() -> Hittable {
         let mat_ground = Material::Lambertian { albedo: Vec3::new(0.5, 0.5, 0.5) };
         let mut world = Hittable::HittableList { hittables : Vec::<Hittable>::new() };
@@ -240,11 +554,11 @@ impl Scene {
         let distrib_zero_one =  Vec2f::new(0.0, 1.0);
         for a in -11..11 {
             for b in -11..11 {
-                let choose_mat = crate::lerp(distrib_zero_one, random() as f32);
+                let choose_mat = crate::lerp(distrib_zero_one, random() as Float);
                 let center = Vec3 {
-                    x: a as f32 + 0.9 * crate::lerp(distrib_zero_one, random() as f32),
+                    x: a as Float + 0.9 * crate::lerp(distrib_zero_one, random() as Float),
                     y: 0.2,
-                    z: b as f32 + 0.9 * crate::lerp(distrib_zero_one, random() as f32),
+                    z: b as Float + 0.9 * crate::lerp(distrib_zero_one, random() as Float),
                 };
                 if (center - Vec3::new(4.0, 0.2, 0.0)).length() > 0.9 {
 
@@ -265,23 +579,23 @@ impl Scene {
                         let distr_fuzz = Vec2f::new(0.0, 0.5);
 
                         let albedo = Vec3::rand(distr_albedo);
-                        let fuzz = crate::lerp(distr_fuzz, random() as f32);
+                        let fuzz = crate::lerp(distr_fuzz, random() as Float);
                         let material = Material::Metal { albedo, fuzz };
                         world.push(
                             Hittable::Sphere {
                                 center,
                                 radius: 0.2,
-                                material: material,
+                                material,
                             }
                         );
                     } else {
                         // glass
-                        let material = Material::Dielectric { index_refraction: 1.5 };
+                        let material = Material::Dielectric { index_refraction: 1.5, dispersion: 0.0 };
                         world.push(
                             Hittable::Sphere{
                                 center,
                                 radius: 0.2,
-                                material: material,
+                                material,
                             }
                         );
 
@@ -290,7 +604,8 @@ impl Scene {
             }
         }
 
-        let material1 = Material::Dielectric { index_refraction: 1.5 };
+        // crown glass with a bit over twice its dispersion, so the rainbow fringes show with `set_spectral()`
+        let material1 = Material::Dielectric { index_refraction: 1.5, dispersion: 0.01 };
         world.push( Hittable::Sphere{
             center: Vec3::new(0.0, 1.0, 0.0),
             radius: 1.0,
@@ -312,4 +627,77 @@ impl Scene {
         });
         world
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_object_ids_survive_removal() {
+        let mut scene = Scene::new(camera(CameraModel::Perspective), Hittable::Sphere {
+            center: Vec3::zero(),
+            radius: 1.0,
+            material: Material::Lambertian { albedo: Vec3::ones() },
+        });
+        let glass = Material::Dielectric { index_refraction: 1.5, dispersion: 0.0 };
+        let first = scene.add_sphere(Vec3::new(2.0, 0.0, 0.0), 0.5, glass);
+        let second = scene.add_sphere(Vec3::new(4.0, 0.0, 0.0), 0.5, glass);
+        assert_eq!((first, second), (1, 2));
+
+        assert!(scene.remove_object(0));
+        assert!(!scene.remove_object(0));
+        assert_eq!(scene.object_count(), 2);
+        assert_eq!(scene.object_index(second), Some(1));
+        assert_eq!(scene.object_id(0), Some(first));
+        // IDs aren't reused
+        assert_eq!(scene.add_sphere(Vec3::zero(), 1.0, glass), 3);
+
+        let metal = Material::Metal { albedo: Vec3::ones(), fuzz: 0.0 };
+        assert!(scene.set_sphere(second, Vec3::new(0.0, 5.0, 0.0), 2.0));
+        assert!(scene.set_material(second, metal));
+        let sphere = scene.object(second).unwrap();
+        assert_eq!((sphere.center, sphere.radius), (Vec3::new(0.0, 5.0, 0.0), 2.0));
+        assert_eq!(scene.material(sphere.material).name(), "metal");
+        // the other glass sphere kept its material
+        assert_eq!(scene.material(scene.object(first).unwrap().material), glass);
+    }
+
+    fn camera(model: CameraModel) -> Camera {
+        // looking down -z from the origin, no lens
+        Camera::new(Vec3::zero(), Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0), 90.0, 2.0, 0.0, 1.0)
+            .with_model(model)
+    }
+
+    #[test]
+    fn test_center_looks_forward() {
+        for model in CameraModel::ALL {
+            let ray = camera(model).get_ray(0.5, 0.5);
+            assert!((Vec3::as_unit(ray.dir) - Vec3::new(0.0, 0.0, -1.0)).near_zero(), "{:?}", model);
+            assert_eq!(CameraModel::from_name(model.name()), Some(model));
+        }
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let camera = camera(CameraModel::Orthographic);
+        let corner = camera.get_ray(0.0, 0.0);
+        assert_eq!(corner.dir, Vec3::new(0.0, 0.0, -1.0));
+        // a 90 degree view at distance 1 is 2 high, and twice as wide
+        assert!((corner.orig - Vec3::new(-2.0, -1.0, 0.0)).near_zero());
+    }
+
+    #[test]
+    fn test_panoramas_cover_the_sphere() {
+        let equirect = camera(CameraModel::Equirectangular);
+        let behind = equirect.get_ray(0.0, 0.5);
+        assert!((Vec3::as_unit(behind.dir) - Vec3::new(0.0, 0.0, 1.0)).near_zero());
+        let right = equirect.get_ray(0.75, 0.5);
+        assert!((Vec3::as_unit(right.dir) - Vec3::new(1.0, 0.0, 0.0)).near_zero());
+        let up = equirect.get_ray(0.3, 1.0);
+        assert!((Vec3::as_unit(up.dir) - Vec3::new(0.0, 1.0, 0.0)).near_zero());
+
+        // the top edge of the fisheye is 90 degrees off the view direction
+        let fisheye = camera(CameraModel::Fisheye);
+        assert!((Vec3::as_unit(fisheye.get_ray(0.5, 1.0).dir) - Vec3::new(0.0, 1.0, 0.0)).near_zero());
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ * Scenes as text: the material library, then the objects, one per line.
+ *
+ *   # material <name> lambertian <albedo rgb>
+ *   # material <name> metal <albedo rgb> <fuzz>
+ *   # material <name> dielectric <index of refraction> <dispersion>
+ *   material ground lambertian 0.5 0.5 0.5
+ *   material glass  dielectric 1.5 0
+ *   # sphere <center xyz> <radius> <material name>
+ *   sphere 0 -1000 0  1000  ground
+ *   sphere 0 1 0      1     glass
+ *
+ * Materials have to come before the spheres using them. Reading keeps the
+ * order of both, so material handles and object indices survive a round
+ * trip (object IDs start over from 0). The camera isn't part of it.
+ */
+
+use std::fmt::Write;
+
+use crate::primitives::{Float, Vec3};
+use crate::scene::{Camera, Hittable, Material, Scene};
+
+pub fn write(scene: &Scene) -> String {
+    let mut text = String::new();
+    for entry in scene.materials.entries() {
+        let params = match entry.material {
+            Material::Lambertian { albedo } => format!("{} {} {}", albedo.x, albedo.y, albedo.z),
+            Material::Metal { albedo, fuzz } => format!("{} {} {} {}", albedo.x, albedo.y, albedo.z, fuzz),
+            Material::Dielectric { index_refraction, dispersion } => format!("{} {}", index_refraction, dispersion),
+        };
+        // `{}` writes the shortest text that reads back to the same float
+        writeln!(text, "material {} {} {}", entry.name, entry.material.name(), params).unwrap();
+    }
+    for index in 0..scene.object_count() {
+        let sphere = scene.world.get(index).expect("Indices below the count are there");
+        let material = &scene.materials.get(sphere.material).expect("Spheres use library entries").name;
+        let center = sphere.center;
+        writeln!(text, "sphere {} {} {} {} {}", center.x, center.y, center.z, sphere.radius, material).unwrap();
+    }
+    return text;
+}
+
+pub fn read(camera: Camera, text: &str) -> Result<Scene, String> {
+    let mut scene = Scene::new(camera, Hittable::HittableList { hittables: Vec::new() });
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |msg: &str| format!("Line {}: {}", line_idx + 1, msg);
+        let mut fields = line.split_whitespace();
+        match fields.next().unwrap_or("") {
+            "material" => {
+                let name = fields.next().ok_or(error("missing material name"))?;
+                let kind = fields.next().unwrap_or("");
+                let values = numbers(fields).map_err(|msg| error(&msg))?;
+                let material = match (kind, values.as_slice()) {
+                    ("lambertian", [r, g, b]) => Material::Lambertian { albedo: Vec3::new(*r, *g, *b) },
+                    ("metal", [r, g, b, fuzz]) => Material::Metal { albedo: Vec3::new(*r, *g, *b), fuzz: *fuzz },
+                    ("dielectric", [index_refraction, dispersion]) => Material::Dielectric {
+                        index_refraction: *index_refraction,
+                        dispersion: *dispersion,
+                    },
+                    ("lambertian", _) => return Err(error("lambertian needs 3 values")),
+                    ("metal", _) => return Err(error("metal needs 4 values")),
+                    ("dielectric", _) => return Err(error("dielectric needs 2 values")),
+                    _ => return Err(error(&format!("unknown material kind: {}", kind))),
+                };
+                scene.materials.add(name, material).map_err(|msg| error(&msg))?;
+            },
+            "sphere" => {
+                let mut fields: Vec<&str> = fields.collect();
+                let material = fields.pop().ok_or(error("missing material name"))?;
+                let handle = scene.materials.handle(material).ok_or(error(&format!("unknown material: {}", material)))?;
+                let values = numbers(fields.into_iter()).map_err(|msg| error(&msg))?;
+                let [x, y, z, radius] = values.as_slice() else {
+                    return Err(error("spheres need a center, a radius and a material"));
+                };
+                scene.add_sphere_with(Vec3::new(*x, *y, *z), *radius, handle);
+            },
+            kind => return Err(error(&format!("unknown line: {}", kind))),
+        }
+    }
+    return Ok(scene);
+}
+
+fn numbers<'a>(fields: impl Iterator<Item = &'a str>) -> Result<Vec<Float>, String> {
+    fields
+        .map(|field| field.parse().map_err(|_| format!("invalid number: {}", field)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random;
+
+    #[test]
+    fn test_round_trip() {
+        random::seed(4);
+        let scene = Scene::random_scene(1.5);
+        let text = write(&scene);
+        let read = read(scene.camera, &text).unwrap();
+        assert_eq!(read.materials.entries(), scene.materials.entries());
+        assert_eq!(read.object_count(), scene.object_count());
+        for index in 0..scene.object_count() {
+            assert_eq!(read.world.get(index), scene.world.get(index));
+        }
+        assert_eq!(write(&read), text);
+    }
+
+    #[test]
+    fn test_errors() {
+        let camera = Scene::random_scene(1.5).camera;
+        assert!(read(camera, "sphere 0 0 0 1 glass").err().unwrap().contains("unknown material"));
+        assert!(read(camera, "material glass dielectric 1.5").err().unwrap().starts_with("Line 1:"));
+        let twice = "material a metal 1 1 1 0\nmaterial a metal 1 1 1 0";
+        assert!(read(camera, twice).err().unwrap().starts_with("Line 2:"));
+        let scene = read(camera, "material a metal 1 1 1 0 # shiny\n\nsphere 0 0 -1 0.5 a").unwrap();
+        assert_eq!(scene.object_count(), 1);
+    }
+}
@@ -0,0 +1,277 @@
+/*
+ * Ray/sphere tests four spheres at a time, behind the "simd" feature.
+ *
+ * Each lane runs the same operations as the scalar test in `spheres`, in
+ * the same order and without fused multiply-adds, so the roots (and the
+ * hits built from them) come out identical to the scalar path.
+ *
+ * The backends: SSE on x86_64 (always there, no detection needed),
+ * simd128 on wasm when it's enabled at compile time, and plain arrays
+ * everywhere else, which the compiler may vectorize by itself.
+ */
+
+use crate::primitives::{Ray, Vec3};
+use crate::scene::{self, HitRecord};
+use crate::spheres::Spheres;
+
+pub const LANES: usize = 4;
+
+// Centers x, y, z and radii of four spheres, one per lane
+pub type Lanes<'a> = [&'a [f32; LANES]; 4];
+
+// Four spheres, for the batch at the end that isn't full
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Spheres4 {
+    pub x: [f32; LANES],
+    pub y: [f32; LANES],
+    pub z: [f32; LANES],
+    pub radius: [f32; LANES],
+}
+
+impl Spheres4 {
+    pub fn set(&mut self, lane: usize, center: Vec3, radius: f32) {
+        self.x[lane] = center.x;
+        self.y[lane] = center.y;
+        self.z[lane] = center.z;
+        self.radius[lane] = radius;
+    }
+
+    pub fn lanes(&self) -> Lanes<'_> {
+        [&self.x, &self.y, &self.z, &self.radius]
+    }
+}
+
+/*
+ * The root of each sphere's intersection with `r` that the scalar test
+ * would pick, infinity where it would miss.
+ */
+#[inline]
+pub fn hit4(spheres: Lanes, r: Ray, t_min: f32, t_max: f32) -> [f32; LANES] {
+    return backend::hit4(spheres, r, t_min, t_max);
+}
+
+#[cfg(target_arch = "x86_64")]
+mod backend {
+    use std::arch::x86_64::*;
+
+    use super::{Lanes, LANES};
+    use crate::primitives::Ray;
+
+    // Lanes of `a` where `mask` is set, `b` elsewhere
+    #[target_feature(enable = "sse2")]
+    fn select(mask: __m128, a: __m128, b: __m128) -> __m128 {
+        _mm_or_ps(_mm_and_ps(mask, a), _mm_andnot_ps(mask, b))
+    }
+
+    #[target_feature(enable = "sse2")]
+    fn load(values: &[f32; LANES]) -> __m128 {
+        _mm_set_ps(values[3], values[2], values[1], values[0])
+    }
+
+    #[inline]
+    pub fn hit4(spheres: Lanes, r: Ray, t_min: f32, t_max: f32) -> [f32; LANES] {
+        // SAFETY: SSE2 is part of every x86_64 CPU
+        unsafe { hit4_sse2(spheres, r, t_min, t_max) }
+    }
+
+    #[target_feature(enable = "sse2")]
+    #[inline]
+    fn hit4_sse2([x, y, z, radius]: Lanes, r: Ray, t_min: f32, t_max: f32) -> [f32; LANES] {
+        let ocx = _mm_sub_ps(_mm_set1_ps(r.orig.x), load(x));
+        let ocy = _mm_sub_ps(_mm_set1_ps(r.orig.y), load(y));
+        let ocz = _mm_sub_ps(_mm_set1_ps(r.orig.z), load(z));
+        let (dx, dy, dz) = (_mm_set1_ps(r.dir.x), _mm_set1_ps(r.dir.y), _mm_set1_ps(r.dir.z));
+        let radius = load(radius);
+
+        let a = _mm_set1_ps(r.dir.length_squared());
+        let half_b = _mm_add_ps(_mm_add_ps(_mm_mul_ps(ocx, dx), _mm_mul_ps(ocy, dy)), _mm_mul_ps(ocz, dz));
+        let oc_squared = _mm_add_ps(_mm_add_ps(_mm_mul_ps(ocx, ocx), _mm_mul_ps(ocy, ocy)), _mm_mul_ps(ocz, ocz));
+        let c = _mm_sub_ps(oc_squared, _mm_mul_ps(radius, radius));
+        let discriminant = _mm_sub_ps(_mm_mul_ps(half_b, half_b), _mm_mul_ps(a, c));
+        let hit = _mm_cmpge_ps(discriminant, _mm_setzero_ps());
+        if _mm_movemask_ps(hit) == 0 {
+            return [f32::INFINITY; LANES]; // the usual case, skip the roots
+        }
+        let sqrtd = _mm_sqrt_ps(discriminant);
+
+        // flipping the sign bit is exactly the scalar negation, -0 included
+        let minus_half_b = _mm_xor_ps(half_b, _mm_set1_ps(-0.0));
+        let near = _mm_div_ps(_mm_sub_ps(minus_half_b, sqrtd), a);
+        let far = _mm_div_ps(_mm_add_ps(minus_half_b, sqrtd), a);
+        let (t_min, t_max) = (_mm_set1_ps(t_min), _mm_set1_ps(t_max));
+        let near_ok = _mm_and_ps(_mm_cmpge_ps(near, t_min), _mm_cmple_ps(near, t_max));
+        let far_ok = _mm_and_ps(_mm_cmpge_ps(far, t_min), _mm_cmple_ps(far, t_max));
+
+        let miss = _mm_set1_ps(f32::INFINITY);
+        let root = select(near_ok, near, select(far_ok, far, miss));
+        let root = select(hit, root, miss);
+        let mut roots = [0.0; LANES];
+        // SAFETY: `roots` has room for the four lanes, and storeu doesn't need alignment
+        unsafe { _mm_storeu_ps(roots.as_mut_ptr(), root) };
+        return roots;
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+mod backend {
+    use core::arch::wasm32::*;
+
+    use super::{Lanes, LANES};
+    use crate::primitives::Ray;
+
+    fn load(values: &[f32; LANES]) -> v128 {
+        f32x4(values[0], values[1], values[2], values[3])
+    }
+
+    #[inline]
+    pub fn hit4([x, y, z, radius]: Lanes, r: Ray, t_min: f32, t_max: f32) -> [f32; LANES] {
+        let ocx = f32x4_sub(f32x4_splat(r.orig.x), load(x));
+        let ocy = f32x4_sub(f32x4_splat(r.orig.y), load(y));
+        let ocz = f32x4_sub(f32x4_splat(r.orig.z), load(z));
+        let (dx, dy, dz) = (f32x4_splat(r.dir.x), f32x4_splat(r.dir.y), f32x4_splat(r.dir.z));
+        let radius = load(radius);
+
+        let a = f32x4_splat(r.dir.length_squared());
+        let half_b = f32x4_add(f32x4_add(f32x4_mul(ocx, dx), f32x4_mul(ocy, dy)), f32x4_mul(ocz, dz));
+        let oc_squared = f32x4_add(f32x4_add(f32x4_mul(ocx, ocx), f32x4_mul(ocy, ocy)), f32x4_mul(ocz, ocz));
+        let c = f32x4_sub(oc_squared, f32x4_mul(radius, radius));
+        let discriminant = f32x4_sub(f32x4_mul(half_b, half_b), f32x4_mul(a, c));
+        let hit = f32x4_ge(discriminant, f32x4_splat(0.0));
+        if !v128_any_true(hit) {
+            return [f32::INFINITY; LANES]; // the usual case, skip the roots
+        }
+        let sqrtd = f32x4_sqrt(discriminant);
+
+        let minus_half_b = f32x4_neg(half_b);
+        let near = f32x4_div(f32x4_sub(minus_half_b, sqrtd), a);
+        let far = f32x4_div(f32x4_add(minus_half_b, sqrtd), a);
+        let (t_min, t_max) = (f32x4_splat(t_min), f32x4_splat(t_max));
+        let near_ok = v128_and(f32x4_ge(near, t_min), f32x4_le(near, t_max));
+        let far_ok = v128_and(f32x4_ge(far, t_min), f32x4_le(far, t_max));
+
+        let miss = f32x4_splat(f32::INFINITY);
+        let root = v128_bitselect(near, v128_bitselect(far, miss, far_ok), near_ok);
+        let root = v128_bitselect(root, miss, hit);
+        return [
+            f32x4_extract_lane::<0>(root),
+            f32x4_extract_lane::<1>(root),
+            f32x4_extract_lane::<2>(root),
+            f32x4_extract_lane::<3>(root),
+        ];
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", all(target_arch = "wasm32", target_feature = "simd128"))))]
+mod backend {
+    use super::{Lanes, LANES};
+    use crate::primitives::Ray;
+
+    #[inline]
+    pub fn hit4([x, y, z, radius]: Lanes, r: Ray, t_min: f32, t_max: f32) -> [f32; LANES] {
+        let a = r.dir.length_squared();
+        let mut roots = [f32::INFINITY; LANES];
+        for (lane, root) in roots.iter_mut().enumerate() {
+            let ocx = r.orig.x - x[lane];
+            let ocy = r.orig.y - y[lane];
+            let ocz = r.orig.z - z[lane];
+            let half_b = ocx * r.dir.x + ocy * r.dir.y + ocz * r.dir.z;
+            let c = (ocx * ocx + ocy * ocy + ocz * ocz) - radius[lane] * radius[lane];
+            let discriminant = half_b * half_b - a * c;
+            let sqrtd = discriminant.sqrt();
+            let near = (-half_b - sqrtd) / a;
+            let far = (-half_b + sqrtd) / a;
+            if discriminant >= 0.0 {
+                if near >= t_min && near <= t_max {
+                    *root = near;
+                } else if far >= t_min && far <= t_max {
+                    *root = far;
+                }
+            }
+        }
+        return roots;
+    }
+}
+
+fn lanes(values: &[f32]) -> &[f32; LANES] {
+    values.try_into().expect("A batch is four lanes")
+}
+
+/*
+ * `Spheres::hit_scalar()` four spheres at a time, straight out of the
+ * store's arrays. The last few go in a padded batch of their own. Batches
+ * go in order and only a strictly nearer hit replaces the best so far, so
+ * ties go to the first sphere like they do there.
+ */
+pub fn hit_spheres(spheres: &Spheres, r: Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+    scene::count_intersection_tests(spheres.len() as u64);
+    let mut nearest: Option<(f32, usize)> = None;
+    let mut closer = |roots: [f32; LANES], first: usize, count: usize| {
+        for (lane, root) in roots.iter().enumerate().take(count) {
+            if *root < nearest.map_or(f32::INFINITY, |(t, _)| t) {
+                nearest = Some((*root, first + lane));
+            }
+        }
+    };
+
+    let batches = spheres.center_x.chunks_exact(LANES)
+        .zip(spheres.center_y.chunks_exact(LANES))
+        .zip(spheres.center_z.chunks_exact(LANES))
+        .zip(spheres.radius.chunks_exact(LANES));
+    for (batch, (((x, y), z), radius)) in batches.enumerate() {
+        closer(hit4([lanes(x), lanes(y), lanes(z), lanes(radius)], r, t_min, t_max), batch * LANES, LANES);
+    }
+    let full = spheres.len() - spheres.len() % LANES;
+    if full < spheres.len() {
+        let mut rest = Spheres4::default();
+        for (lane, index) in (full..spheres.len()).enumerate() {
+            rest.set(lane, spheres.center(index), spheres.radius[index]);
+        }
+        closer(hit4(rest.lanes(), r, t_min, t_max), full, spheres.len() - full);
+    }
+
+    let (root, index) = nearest?;
+    return Some(spheres.record(r, index, root));
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random::{self, random};
+    use crate::scene::Scene;
+    use crate::spheres::Sphere;
+
+    #[test]
+    fn test_matches_scalar() {
+        random::seed(9);
+        let scene = Scene::random_scene(1.5);
+        for _ in 0..2000 {
+            let ray = scene.camera.get_ray(random() as f32, random() as f32);
+            let simd = hit_spheres(&scene.world, ray, 0.001, f32::INFINITY);
+            let scalar = scene.world.hit_scalar(ray, 0.001, f32::INFINITY);
+            match (simd, scalar) {
+                (None, None) => {},
+                (Some(simd), Some(scalar)) => {
+                    assert_eq!(simd.object_index, scalar.object_index);
+                    assert_eq!(simd.t, scalar.t);
+                    assert_eq!(simd.p, scalar.p);
+                    assert_eq!(simd.normal, scalar.normal);
+                },
+                _ => panic!("Only one of them hit"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_inside_and_behind() {
+        let mut spheres = Spheres::default();
+        for (z, radius) in [(-3.0, 0.5), (0.0, 2.0), (3.0, 0.5), (-8.0, 1.0), (6.0, 1.0)] {
+            spheres.push(Sphere { center: Vec3::new(0.0, 0.0, z), radius, material: 0 });
+        }
+        let ray = Ray { orig: Vec3::zero(), dir: Vec3::new(0.0, 0.0, -1.0) };
+        // from inside the big sphere: its far side (t = 2) is nearer than the one in front
+        let hit = hit_spheres(&spheres, ray, 0.001, f32::INFINITY).unwrap();
+        assert_eq!((hit.object_index, hit.t, hit.front_face), (1, 2.0, false));
+        // the fifth sphere (the padded batch) is behind, the others are out of range
+        assert!(hit_spheres(&spheres, ray, 0.001, 1.5).is_none());
+    }
+}
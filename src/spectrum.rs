@@ -0,0 +1,222 @@
+use std::sync::OnceLock;
+
+use crate::random::random;
+
+use crate::primitives::{Float, Vec2f, Vec3};
+
+// Visible range covered by the RGB -> spectrum upsampling tables (nanometers)
+pub const LAMBDA_MIN: Float = 380.0;
+pub const LAMBDA_MAX: Float = 720.0;
+
+// Wavelength at which a material's `index_refraction` is specified (the
+// Fraunhofer d-line, same as the catalog values for glass).
+const LAMBDA_D: Float = 587.6;
+
+/*
+ * Smits' basis spectra for RGB -> reflectance upsampling.
+ *
+ * 10 evenly spaced bins covering LAMBDA_MIN..LAMBDA_MAX. See "An RGB to
+ * Spectrum Conversion for Reflectances" (Smits, 1999).
+ */
+const SMITS_BINS: usize = 10;
+const SMITS_WHITE: [Float; SMITS_BINS] =   [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [Float; SMITS_BINS] =    [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [Float; SMITS_BINS] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [Float; SMITS_BINS] =  [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [Float; SMITS_BINS] =     [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [Float; SMITS_BINS] =   [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [Float; SMITS_BINS] =    [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+// How the renderer represents color while tracing paths
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ColorMode {
+    Rgb,
+    // every camera sample traces this many (stratified) wavelengths
+    Spectral { wavelengths: u32 },
+}
+
+/*
+ * Picks `count` wavelengths stratified over the visible range, sharing a
+ * single random offset. All of them have the same (uniform) PDF.
+ */
+pub fn sample_wavelengths(count: u32) -> impl Iterator<Item = Float> {
+    let offset = crate::lerp(Vec2f::new(0.0, 1.0), random() as Float);
+    (0..count).map(move |i| {
+        let u = (i as Float + offset) / count as Float;
+        crate::lerp(Vec2f::new(LAMBDA_MIN, LAMBDA_MAX), u)
+    })
+}
+
+fn smits_bin(spectrum: &[Float; SMITS_BINS], lambda: Float) -> Float {
+    let u = (lambda - LAMBDA_MIN) / (LAMBDA_MAX - LAMBDA_MIN);
+    let idx = (u * SMITS_BINS as Float) as i32;
+    spectrum[idx.clamp(0, SMITS_BINS as i32 - 1) as usize]
+}
+
+// Evaluates the spectral reflectance of an RGB color at `lambda`
+pub fn rgb_to_spectrum(rgb: Vec3, lambda: Float) -> Float {
+    let white = smits_bin(&SMITS_WHITE, lambda);
+    let cyan = smits_bin(&SMITS_CYAN, lambda);
+    let magenta = smits_bin(&SMITS_MAGENTA, lambda);
+    let yellow = smits_bin(&SMITS_YELLOW, lambda);
+    let red = smits_bin(&SMITS_RED, lambda);
+    let green = smits_bin(&SMITS_GREEN, lambda);
+    let blue = smits_bin(&SMITS_BLUE, lambda);
+
+    let (r, g, b) = (rgb.x, rgb.y, rgb.z);
+    if r <= g && r <= b {
+        // red is the smallest component
+        r * white + if g <= b {
+            (g - r) * cyan + (b - g) * blue
+        } else {
+            (b - r) * cyan + (g - b) * green
+        }
+    } else if g <= r && g <= b {
+        // green is the smallest component
+        g * white + if r <= b {
+            (r - g) * magenta + (b - r) * blue
+        } else {
+            (b - g) * magenta + (r - b) * red
+        }
+    } else {
+        // blue is the smallest component
+        b * white + if r <= g {
+            (r - b) * yellow + (g - r) * green
+        } else {
+            (g - b) * yellow + (r - g) * red
+        }
+    }
+}
+
+fn piecewise_gaussian(x: Float, mu: Float, sigma_lo: Float, sigma_hi: Float) -> Float {
+    let sigma = if x < mu { sigma_lo } else { sigma_hi };
+    let t = (x - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/*
+ * CIE 1931 standard observer color matching functions.
+ *
+ * Uses the multi-lobe analytic fit from "Simple Analytic Approximations to
+ * the CIE XYZ Color Matching Functions" (Wyman, Sloan, Shirley, 2013).
+ */
+pub fn cie_xyz(lambda: Float) -> Vec3 {
+    Vec3 {
+        x: 1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+            + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+            - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2),
+        y: 0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+            + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1),
+        z: 1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+            + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8),
+    }
+}
+
+// XYZ -> linear sRGB (D65)
+pub fn xyz_to_rgb(xyz: Vec3) -> Vec3 {
+    Vec3 {
+        x:  3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        y: -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        z:  0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    }
+}
+
+/*
+ * Integrated response of the color matching functions over the sampled range.
+ *
+ * The Y integral normalizes estimates so that a constant spectrum of 1.0 has
+ * luminance 1.0, and the RGB of that constant spectrum is used to white
+ * balance so it comes out as (1, 1, 1) like it would in RGB mode.
+ */
+struct Normalization {
+    y_integral: Float,
+    white_rgb: Vec3,
+}
+
+fn normalization() -> &'static Normalization {
+    static NORM: OnceLock<Normalization> = OnceLock::new();
+    NORM.get_or_init(|| {
+        let steps = (LAMBDA_MAX - LAMBDA_MIN) as i32;
+        let xyz = (0..steps).fold(Vec3::zero(), |acc, step| {
+            acc + cie_xyz(LAMBDA_MIN + step as Float + 0.5)
+        });
+        Normalization {
+            y_integral: xyz.y,
+            white_rgb: xyz_to_rgb(xyz / xyz.y),
+        }
+    })
+}
+
+/*
+ * Monte-Carlo estimate of XYZ for a single radiance sample at `lambda`.
+ *
+ * Wavelengths are drawn uniformly (see `sample_wavelengths`), so the estimate
+ * is divided by that PDF.
+ */
+pub fn radiance_to_xyz(lambda: Float, radiance: Float) -> Vec3 {
+    let pdf = 1.0 / (LAMBDA_MAX - LAMBDA_MIN);
+    cie_xyz(lambda) * (radiance / pdf / normalization().y_integral)
+}
+
+// Converts an accumulated XYZ value to white balanced, linear sRGB
+pub fn xyz_to_balanced_rgb(xyz: Vec3) -> Vec3 {
+    xyz_to_rgb(xyz) / normalization().white_rgb
+}
+
+/*
+ * Index of refraction at `lambda` using Cauchy's equation.
+ *
+ * `ior_d` is the index at the d-line and `dispersion` is Cauchy's B
+ * coefficient in square micrometers (about 0.0042 for BK7 glass). A
+ * dispersion of 0.0 gives a constant index.
+ */
+pub fn cauchy_ior(ior_d: Float, dispersion: Float, lambda: Float) -> Float {
+    let lambda_um = lambda / 1000.0;
+    let lambda_d_um = LAMBDA_D / 1000.0;
+    ior_d + dispersion * (1.0 / (lambda_um * lambda_um) - 1.0 / (lambda_d_um * lambda_d_um))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn integrate_rgb(rgb: Vec3) -> Vec3 {
+        let steps = (LAMBDA_MAX - LAMBDA_MIN) as i32;
+        let xyz = (0..steps).fold(Vec3::zero(), |acc, step| {
+            let lambda = LAMBDA_MIN + step as Float + 0.5;
+            // one sample per nanometer, undo the uniform PDF
+            acc + radiance_to_xyz(lambda, rgb_to_spectrum(rgb, lambda)) / (LAMBDA_MAX - LAMBDA_MIN)
+        });
+        xyz_to_balanced_rgb(xyz)
+    }
+
+    #[test]
+    fn test_white_round_trip() {
+        let rgb = integrate_rgb(Vec3::ones());
+        let diff = rgb - Vec3::ones();
+        assert!(diff.length() < 0.01, "White came back as {}", rgb);
+    }
+
+    #[test]
+    fn test_gray_round_trip() {
+        let rgb = integrate_rgb(Vec3::new(0.5, 0.5, 0.5));
+        let diff = rgb - Vec3::new(0.5, 0.5, 0.5);
+        assert!(diff.length() < 0.01, "Gray came back as {}", rgb);
+    }
+
+    #[test]
+    fn test_red_stays_red() {
+        let rgb = integrate_rgb(Vec3::new(1.0, 0.0, 0.0));
+        assert!(rgb.x > 0.5);
+        assert!(rgb.x > rgb.y * 4.0);
+        assert!(rgb.x > rgb.z * 4.0);
+    }
+
+    #[test]
+    fn test_cauchy_dispersion() {
+        assert_eq!(cauchy_ior(1.5, 0.0, 450.0), 1.5);
+        assert!((cauchy_ior(1.5, 0.0042, LAMBDA_D) - 1.5).abs() < 1e-6);
+        // blue bends more than red
+        assert!(cauchy_ior(1.5, 0.0042, 450.0) > cauchy_ior(1.5, 0.0042, 650.0));
+    }
+}
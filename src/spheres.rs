@@ -0,0 +1,222 @@
+/*
+ * The spheres of a scene, flattened into one array per field.
+ *
+ * Intersecting a ray with the scene only reads the centers and radii, so
+ * those are packed together (and line up for the four-wide tests of the
+ * "simd" feature); the materials are indices into the scene's material
+ * table, looked up once there's a hit.
+ */
+
+use crate::primitives::{Float, Ray, Vec3, gamma};
+use crate::scene::{self, HitRecord};
+#[cfg(feature = "simd")]
+use crate::simd;
+use crate::stats;
+
+// One sphere of the store, as it's read and edited
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: Float,
+    pub material: u32, // index into `Scene::materials`
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Spheres {
+    pub(crate) center_x: Vec<Float>,
+    pub(crate) center_y: Vec<Float>,
+    pub(crate) center_z: Vec<Float>,
+    pub(crate) radius: Vec<Float>,
+    pub(crate) material: Vec<u32>,
+}
+
+impl Spheres {
+    pub fn len(&self) -> usize {
+        self.radius.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.radius.is_empty()
+    }
+
+    pub fn push(&mut self, sphere: Sphere) {
+        self.center_x.push(sphere.center.x);
+        self.center_y.push(sphere.center.y);
+        self.center_z.push(sphere.center.z);
+        self.radius.push(sphere.radius);
+        self.material.push(sphere.material);
+    }
+
+    pub fn get(&self, index: usize) -> Option<Sphere> {
+        if index >= self.len() {
+            return None;
+        }
+        return Some(Sphere {
+            center: self.center(index),
+            radius: self.radius[index],
+            material: self.material[index],
+        });
+    }
+
+    // False if there's no such sphere
+    pub fn set(&mut self, index: usize, sphere: Sphere) -> bool {
+        if index >= self.len() {
+            return false;
+        }
+        self.center_x[index] = sphere.center.x;
+        self.center_y[index] = sphere.center.y;
+        self.center_z[index] = sphere.center.z;
+        self.radius[index] = sphere.radius;
+        self.material[index] = sphere.material;
+        return true;
+    }
+
+    // The ones after it move down one index
+    pub fn remove(&mut self, index: usize) {
+        self.center_x.remove(index);
+        self.center_y.remove(index);
+        self.center_z.remove(index);
+        self.radius.remove(index);
+        self.material.remove(index);
+    }
+
+    pub(crate) fn center(&self, index: usize) -> Vec3 {
+        Vec3::new(self.center_x[index], self.center_y[index], self.center_z[index])
+    }
+
+    // Nearest hit along `r` within `t_min..=t_max`, ties go to the first sphere
+    pub fn hit(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        stats::count_ray();
+        #[cfg(feature = "simd")]
+        return simd::hit_spheres(self, r, t_min, t_max);
+        #[cfg(not(feature = "simd"))]
+        return self.hit_scalar(r, t_min, t_max);
+    }
+
+    // One sphere at a time
+    #[cfg_attr(feature = "simd", allow(dead_code))] // still the reference for the SIMD one
+    pub(crate) fn hit_scalar(&self, r: Ray, t_min: Float, t_max: Float) -> Option<HitRecord> {
+        scene::count_intersection_tests(self.len() as u64);
+        let mut nearest: Option<(Float, usize)> = None;
+        let spheres = self.center_x.iter()
+            .zip(&self.center_y)
+            .zip(&self.center_z)
+            .zip(&self.radius);
+        for (index, (((x, y), z), radius)) in spheres.enumerate() {
+            // nothing past the nearest hit so far can win, a tie stays with the first
+            let t_max = nearest.map_or(t_max, |(t, _)| t);
+            let Some(root) = sphere_root(r, Vec3::new(*x, *y, *z), *radius, t_min, t_max) else {
+                continue;
+            };
+            if nearest.is_none_or(|(t, _)| root < t) {
+                nearest = Some((root, index));
+            }
+        }
+        let (root, index) = nearest?;
+        return Some(self.record(r, index, root));
+    }
+
+    // The hit at `root`, a root of sphere `index`'s intersection with `r`
+    pub(crate) fn record(&self, r: Ray, index: usize, root: Float) -> HitRecord {
+        let center = self.center(index);
+        let radius = self.radius[index];
+        // back onto the surface, which takes most of the error of `r.at()` out
+        let mut p_local = r.at(root) - center;
+        p_local *= radius.abs() / p_local.length();
+        let p = center + p_local;
+        let mut record = HitRecord{
+            p,
+            // the reprojection, then adding the center back (PBRT 3.9.4)
+            p_error: p_local.abs() * gamma(5) + p.abs() * gamma(1),
+            normal: p_local / radius,
+            material: self.material[index],
+            t: root,
+            front_face: false,
+            object_index: index as u32,
+        };
+        let outward_normal = p_local / radius;
+        record.set_face_normal(r, outward_normal);
+        return record;
+    }
+}
+
+// The nearest of the two roots that lies within tolerance
+fn sphere_root(r: Ray, center: Vec3, radius: Float, t_min: Float, t_max: Float) -> Option<Float> {
+    let oc = r.orig - center;
+    let a = r.dir.length_squared();
+    let half_b = Vec3::dot(oc, r.dir);
+    let c = oc.length_squared() - radius * radius;
+    let discriminant = half_b*half_b - a*c;
+
+    if discriminant < 0.0 {
+        return None;
+    }
+    let sqrtd = discriminant.sqrt();
+
+    let mut root = (-half_b - sqrtd) / a;
+    if root < t_min || root > t_max {
+        root = (-half_b + sqrtd) / a;
+        if root < t_min || root > t_max {
+            return None;
+        }
+    }
+    return Some(root);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random;
+
+    #[test]
+    fn test_nearest_hit() {
+        let mut spheres = Spheres::default();
+        for (z, radius, material) in [(-3.0, 0.5, 0), (0.0, 2.0, 1), (3.0, 0.5, 0), (-8.0, 1.0, 2)] {
+            spheres.push(Sphere { center: Vec3::new(0.0, 0.0, z), radius, material });
+        }
+        let ray = Ray { orig: Vec3::zero(), dir: Vec3::new(0.0, 0.0, -1.0) };
+        // from inside the big sphere: its far side (t = 2) is nearer than the one in front
+        let hit = spheres.hit(ray, 0.001, Float::INFINITY).unwrap();
+        assert_eq!((hit.object_index, hit.material, hit.t, hit.front_face), (1, 1, 2.0, false));
+        assert!(spheres.hit(ray, 0.001, 1.5).is_none());
+
+        spheres.remove(1);
+        let hit = spheres.hit(ray, 0.001, Float::INFINITY).unwrap();
+        assert_eq!((hit.object_index, hit.material, hit.t, hit.front_face), (0, 0, 2.5, true));
+        assert_eq!(spheres.get(2).map(|sphere| sphere.material), Some(2));
+        assert_eq!(spheres.get(3), None);
+    }
+
+    #[test]
+    fn test_no_self_intersection() {
+        random::seed(7);
+        // far smaller than the old fixed epsilon, and big and far enough out
+        // for f32 hit points to be off by more than it
+        for (center, radius) in [
+            (Vec3::zero(), 1e-4),
+            (Vec3::new(3.0, -2.0, 1.0), 1e-3),
+            (Vec3::new(0.0, -1e5, 0.0), 1e5),
+            (Vec3::new(2e3, 0.0, -5e3), 1e3),
+        ] {
+            let mut spheres = Spheres::default();
+            spheres.push(Sphere { center, radius, material: 0 });
+            for _ in 0..2000 {
+                // from somewhere in front of the point, not grazing it
+                let normal = Vec3::rand_unit_vector();
+                let orig = center + (normal * 3.0 + Vec3::rand_unit_vector()) * radius;
+                let ray = Ray { orig, dir: center + normal * radius - orig };
+                let hit = spheres.hit(ray, 0.0, Float::INFINITY).unwrap();
+                assert!(hit.front_face);
+
+                // bouncing off can't hit the sphere again
+                let out = hit.normal + Vec3::rand_unit_vector() * 0.99;
+                assert!(spheres.hit(hit.spawn_ray(out), 0.0, Float::INFINITY).is_none(), "{} {}", radius, hit.p);
+
+                // going in has to come out the other side, not hit the way in
+                let into = -hit.normal + Vec3::rand_unit_vector() * 0.99;
+                let inside = spheres.hit(hit.spawn_ray(into), 0.0, Float::INFINITY);
+                assert!(inside.is_some_and(|inside| !inside.front_face), "{} {}", radius, hit.p);
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+/*
+ * Numbers about a render: how much work it took and how long.
+ *
+ * The work is counted in thread locals as it happens (like the
+ * intersection tests in `scene`), and a render takes the difference
+ * between the counters before and after. Rendering is single threaded, so
+ * everything counted in between belongs to it.
+ */
+
+use std::cell::Cell;
+
+use wasm_bindgen::prelude::*;
+
+use crate::primitives::Vec2i;
+use crate::scene;
+
+thread_local! {
+    static PRIMARY_RAYS: Cell<u64> = const { Cell::new(0) };
+    static RAYS: Cell<u64> = const { Cell::new(0) };
+    static PATH_RAYS: Cell<u64> = const { Cell::new(0) };
+}
+
+// A ray from the camera, one per sample
+pub fn count_primary_ray() {
+    PRIMARY_RAYS.with(|rays| rays.set(rays.get() + 1));
+}
+
+// Any ray intersected with the scene
+pub fn count_ray() {
+    RAYS.with(|rays| rays.set(rays.get() + 1));
+}
+
+// Rays the integrator traced for a sample (the rest are for the AOVs)
+pub fn count_path_rays(count: u64) {
+    PATH_RAYS.with(|rays| rays.set(rays.get() + count));
+}
+
+pub fn rays() -> u64 {
+    RAYS.with(|rays| rays.get())
+}
+
+// Everything counted by the current thread so far
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub primary_rays: u64,
+    pub rays: u64,
+    pub path_rays: u64,
+    pub intersection_tests: u64,
+}
+
+impl Counters {
+    pub fn now() -> Counters {
+        Counters {
+            primary_rays: PRIMARY_RAYS.with(|rays| rays.get()),
+            rays: rays(),
+            path_rays: PATH_RAYS.with(|rays| rays.get()),
+            intersection_tests: scene::intersection_tests(),
+        }
+    }
+
+    // What was counted after `start`
+    pub fn since(&self, start: &Counters) -> Counters {
+        Counters {
+            primary_rays: self.primary_rays - start.primary_rays,
+            rays: self.rays - start.rays,
+            path_rays: self.path_rays - start.path_rays,
+            intersection_tests: self.intersection_tests - start.intersection_tests,
+        }
+    }
+
+    fn add(&mut self, other: &Counters) {
+        self.primary_rays += other.primary_rays;
+        self.rays += other.rays;
+        self.path_rays += other.path_rays;
+        self.intersection_tests += other.intersection_tests;
+    }
+}
+
+// Milliseconds from some fixed point, `Instant` isn't there on the web
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+    static START: OnceLock<Instant> = OnceLock::new();
+    START.get_or_init(Instant::now).elapsed().as_secs_f64() * 1000.0
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub pixels: u32,
+    pub samples_per_pixel: u32, // the average, with adaptive sampling
+    pub counters: Counters,
+    pub milliseconds: f64, // spent rendering, not waiting for the browser
+    pub tile_milliseconds: Vec<f64>, // top tile first, empty for adaptive renders
+}
+
+impl RenderStats {
+    pub fn new(img_size: Vec2i, samples_per_pixel: u32) -> RenderStats {
+        RenderStats {
+            pixels: (img_size.x * img_size.y) as u32,
+            samples_per_pixel,
+            ..RenderStats::default()
+        }
+    }
+
+    // Runs `render`, counting what it does
+    pub fn measure<T>(&mut self, render: impl FnOnce() -> T) -> (T, f64) {
+        let counters = Counters::now();
+        let start = now_ms();
+        let result = render();
+        let milliseconds = now_ms() - start;
+        self.counters.add(&Counters::now().since(&counters));
+        self.milliseconds += milliseconds;
+        return (result, milliseconds);
+    }
+
+    /*
+     * Adds the stats of another pass over the same pixels (progressive
+     * rendering), the tile times add up tile by tile.
+     */
+    pub fn merge(&mut self, pass: &RenderStats) {
+        self.samples_per_pixel += pass.samples_per_pixel;
+        self.counters.add(&pass.counters);
+        self.milliseconds += pass.milliseconds;
+        if self.tile_milliseconds.len() == pass.tile_milliseconds.len() {
+            for (total, tile) in self.tile_milliseconds.iter_mut().zip(&pass.tile_milliseconds) {
+                *total += tile;
+            }
+        } else {
+            self.tile_milliseconds = pass.tile_milliseconds.clone();
+        }
+    }
+
+    pub fn tiles(&self) -> u32 {
+        self.tile_milliseconds.len() as u32
+    }
+
+    // Rays the integrator traced per camera ray, 1 when nothing bounces
+    pub fn average_path_length(&self) -> f64 {
+        if self.counters.primary_rays == 0 {
+            return 0.0;
+        }
+        return self.counters.path_rays as f64 / self.counters.primary_rays as f64;
+    }
+
+    pub fn rays_per_second(&self) -> f64 {
+        if self.milliseconds <= 0.0 {
+            return 0.0;
+        }
+        return self.counters.rays as f64 / (self.milliseconds / 1000.0);
+    }
+
+    // A few lines for people, what the canvas overlay and the CLI show
+    pub fn summary(&self) -> Vec<String> {
+        vec![
+            format!("{} px x {} spp in {:.0} ms", self.pixels, self.samples_per_pixel, self.milliseconds),
+            format!(
+                "{} rays ({} primary), {:.2} Mrays/s",
+                self.counters.rays,
+                self.counters.primary_rays,
+                self.rays_per_second() / 1e6
+            ),
+            format!("{} intersection tests", self.counters.intersection_tests),
+            format!("average path length {:.2}", self.average_path_length()),
+        ]
+    }
+
+    /*
+     * A plain JS object with the same fields, the counters flattened into
+     * it and the derived numbers added. Counts come out as numbers, they
+     * stay exact up to 2^53.
+     */
+    pub fn to_js(&self) -> JsValue {
+        let object = js_sys::Object::new();
+        let set = |key: &str, value: JsValue| {
+            js_sys::Reflect::set(&object, &JsValue::from_str(key), &value).expect("Could not set stats field");
+        };
+        set("pixels", self.pixels.into());
+        set("samples_per_pixel", self.samples_per_pixel.into());
+        set("primary_rays", (self.counters.primary_rays as f64).into());
+        set("rays", (self.counters.rays as f64).into());
+        set("intersection_tests", (self.counters.intersection_tests as f64).into());
+        set("average_path_length", self.average_path_length().into());
+        set("rays_per_second", self.rays_per_second().into());
+        set("milliseconds", self.milliseconds.into());
+        set("tiles", self.tiles().into());
+        let tiles: js_sys::Array = self.tile_milliseconds.iter().map(|ms| JsValue::from_f64(*ms)).collect();
+        set("tile_milliseconds", tiles.into());
+        return object.into();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::aov::Aov;
+    use crate::random;
+    use crate::renderer::{self, RenderProperties};
+    use crate::scene::Scene;
+
+    #[test]
+    fn test_counters_add_up() {
+        let img_size = Vec2i::new(12, 8);
+        random::seed(11);
+        let scene = Scene::random_scene(1.5);
+        let properties = RenderProperties {
+            samples: 3,
+            bounces: 8,
+            aovs: vec![Aov::Depth],
+            roulette_depth: None,
+            ..Default::default()
+        };
+        let (_, stats) = renderer::render_image_with_stats(img_size, &scene, &properties);
+        let counters = stats.counters;
+        assert_eq!(counters.primary_rays, 12 * 8 * 3);
+        // the depth comes from the path's first hit, it takes no rays of its own
+        assert_eq!(counters.rays, counters.path_rays);
+        // no acceleration structure, every ray is tested against everything
+        assert_eq!(counters.intersection_tests, counters.rays * scene.object_count() as u64);
+        assert!(stats.average_path_length() > 1.0 && stats.average_path_length() <= 8.0);
+        assert_eq!(stats.tiles(), 8);
+
+        let mut total = stats.clone();
+        total.merge(&stats);
+        assert_eq!(total.samples_per_pixel, 6);
+        assert_eq!(total.counters.rays, counters.rays * 2);
+        assert_eq!(total.average_path_length(), stats.average_path_length());
+    }
+}